@@ -2,44 +2,205 @@ use std::path::Path;
 use std::fs::File;
 use std::io::Read;
 use std::env;
+use std::fmt;
+use std::io::{Seek, SeekFrom};
+
+const CHUNK_SIZE: usize = 64 * 1024;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
-    match args.get(1) {
+    let filename = match args.get(1) {
         None => {
             println!("No file name given");
             return;
         }
-        Some(filename) => {
-            let input = read_and_parse(&Path::new(filename));
-            match input {
-                Ok(digits) => {
-                    println!("Checksum is: {}", calc_sum_part2(&digits));
-                }
-                Err(err) => {
-                    println!("Unable to read input: {}", err);
-                }
+        Some(filename) => filename,
+    };
+    let result = parse_options(&args[2..]).and_then(|options| run(Path::new(filename), &options));
+    match result {
+        Ok(output) => println!("{}", output),
+        Err(err) => println!("{}", err),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Part2,
+    Stream(Option<usize>),
+    Spectrum(SpectrumFormat),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpectrumFormat {
+    Table,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Options {
+    mode: Mode,
+    radix: u32,
+    strict: bool,
+}
+
+fn parse_options(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        mode: Mode::Part2,
+        radix: 10,
+        strict: false,
+    };
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--stream" => {
+                let step = match args.next_if(|s| !s.starts_with("--")) {
+                    None => None,
+                    Some(step) => Some(
+                        step.parse::<usize>()
+                            .map_err(|err| format!("Invalid step '{}': {}", step, err))?,
+                    ),
+                };
+                options.mode = Mode::Stream(step);
+            }
+            "--spectrum" => {
+                let format = match args.next_if(|s| !s.starts_with("--")).map(|s| s.as_str()) {
+                    None | Some("table") => SpectrumFormat::Table,
+                    Some("csv") => SpectrumFormat::Csv,
+                    Some(format) => {
+                        return Err(format!(
+                            "Unknown spectrum format '{}', expected table or csv",
+                            format
+                        ))
+                    }
+                };
+                options.mode = Mode::Spectrum(format);
+            }
+            "--radix" => {
+                let radix = args.next().ok_or_else(|| "No radix given".to_owned())?;
+                options.radix = radix
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|r| (2..=36).contains(r))
+                    .ok_or_else(|| format!("Invalid radix '{}', expected 2 to 36", radix))?;
             }
+            "--strict" => options.strict = true,
+            option => return Err(format!("Unknown option '{}'", option)),
         }
     }
+    if options.strict && matches!(options.mode, Mode::Stream(_)) {
+        return Err("Strict parsing is not available in streaming mode".to_owned());
+    }
+    Ok(options)
+}
+
+fn run(path: &Path, options: &Options) -> Result<String, String> {
+    if let Mode::Stream(step) = options.mode {
+        let sum = run_streaming(path, step, options.radix)
+            .map_err(|err| format!("Unable to read input: {}", err))?;
+        return Ok(format!("Checksum is: {}", sum));
+    }
+    let input = read_input(path).map_err(|err| format!("Unable to read input: {}", err))?;
+    let digits = if options.strict {
+        parse_strict(&input, options.radix).map_err(|invalid| {
+            invalid
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>()
+                .join("\n")
+        })?
+    } else if options.radix == 10 {
+        parse(&input)
+    } else {
+        parse_radix(&input, options.radix)
+    };
+    match options.mode {
+        Mode::Spectrum(SpectrumFormat::Table) => {
+            Ok(format_spectrum_table(&calc_sum_spectrum(&digits)?))
+        }
+        Mode::Spectrum(SpectrumFormat::Csv) => {
+            Ok(format_spectrum_csv(&calc_sum_spectrum(&digits)?))
+        }
+        _ => Ok(format!("Checksum is: {}", calc_sum_part2(&digits))),
+    }
 }
 
-fn read_and_parse(path: &Path) -> std::io::Result<Vec<u8>> {
+fn run_streaming(path: &Path, step: Option<usize>, radix: u32) -> std::io::Result<u64> {
+    let mut file = File::open(path)?;
+    match step {
+        None => calc_sum_part2_streaming(&mut file, radix),
+        Some(step) => calc_sum_streaming(&mut file, step, radix),
+    }
+}
+
+fn read_input(path: &Path) -> std::io::Result<String> {
     let mut file = File::open(path)?;
     let mut input = String::with_capacity(1024);
     file.read_to_string(&mut input)?;
-    let input = input;
-
-    return Ok(parse(&input));
+    Ok(input)
 }
 
+#[allow(clippy::needless_return)]
 fn parse(input: &str) -> Vec<u8> {
-    return input
+    return parse_radix(input, 10);
+}
+
+fn parse_radix(input: &str, radix: u32) -> Vec<u8> {
+    input
         .chars()
-        .filter_map(|c| c.to_digit(10).map(|d| d as u8))
-        .collect();
+        .filter_map(|c| c.to_digit(radix).map(|d| d as u8))
+        .collect()
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct InvalidChar {
+    character: char,
+    line: usize,
+    column: usize,
+    offset: usize,
+}
+
+impl fmt::Display for InvalidChar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid character {:?} at line {}, column {} (byte offset {})",
+            self.character, self.line, self.column, self.offset
+        )
+    }
+}
+
+// Reports every character that is neither a digit nor whitespace. Whitespace, including line
+// breaks, tabs and non-breaking spaces, is deliberately ignored so that the digits may be
+// wrapped and grouped in strict mode, too.
+fn parse_strict(input: &str, radix: u32) -> Result<Vec<u8>, Vec<InvalidChar>> {
+    let mut digits: Vec<u8> = Vec::with_capacity(input.len());
+    let mut invalid: Vec<InvalidChar> = Vec::new();
+    let mut line = 1;
+    let mut column = 0;
+    for (offset, character) in input.char_indices() {
+        column += 1;
+        if character == '\n' {
+            line += 1;
+            column = 0;
+        } else if let Some(digit) = character.to_digit(radix) {
+            digits.push(digit as u8);
+        } else if !character.is_whitespace() {
+            invalid.push(InvalidChar {
+                character,
+                line,
+                column,
+                offset,
+            });
+        }
+    }
+    if invalid.is_empty() {
+        Ok(digits)
+    } else {
+        Err(invalid)
+    }
+}
+
+#[allow(clippy::needless_return, clippy::assign_op_pattern)]
 fn calc_sum(digits: &[u8], step: usize) -> u32 {
     let len = digits.len();
     if len < 2 {
@@ -55,15 +216,273 @@ fn calc_sum(digits: &[u8], step: usize) -> u32 {
     return sum;
 }
 
+#[allow(clippy::needless_return)]
 fn calc_sum_part2(digits: &[u8]) -> u32 {
     return calc_sum(digits, digits.len() / 2);
 }
 
+// Sums for the steps 1..=len/2; index 0 of the result belongs to step 1.
+// For every digit value, the number of matches for each step is the cyclic autocorrelation of
+// the positions holding that value, which is computed for all steps at once with an NTT.
+fn calc_sum_spectrum(digits: &[u8]) -> Result<Vec<u32>, String> {
+    let len = digits.len();
+    if len < 2 {
+        return Ok(Vec::new());
+    }
+    let size = spectrum_transform_size(len)?;
+    let mut sums: Vec<u32> = vec![0; len / 2];
+    let max_digit = digits.iter().max().cloned().unwrap_or(0);
+    for value in 1..=max_digit {
+        if !digits.contains(&value) {
+            continue;
+        }
+        let mut forward: Vec<u64> = vec![0; size];
+        let mut reversed: Vec<u64> = vec![0; size];
+        for (i, &digit) in digits.iter().enumerate() {
+            if digit == value {
+                forward[i] = 1;
+                reversed[len - 1 - i] = 1;
+            }
+        }
+        ntt(&mut forward, false);
+        ntt(&mut reversed, false);
+        for (f, r) in forward.iter_mut().zip(reversed.iter()) {
+            *f = *f * r % NTT_MODULUS;
+        }
+        ntt(&mut forward, true);
+        // forward[len - 1 + k] now counts the positions i with digits[i] == digits[i + k] == value
+        for (step, sum) in (1..).zip(sums.iter_mut()) {
+            let matches = forward[len - 1 + step] + forward[step - 1];
+            *sum += matches as u32 * value as u32;
+        }
+    }
+    Ok(sums)
+}
+
+// NTT_MODULUS is 15 * 2^27 + 1 with the primitive root NTT_ROOT, so roots of unity exist for
+// transform sizes up to 2^27. The square of a residue still fits into a u64.
+const NTT_MODULUS: u64 = 2_013_265_921;
+const NTT_ROOT: u64 = 31;
+const NTT_MAX_SIZE: usize = 1 << 27;
+
+// The transform has to hold the digits and their reversed copy without overlapping.
+fn spectrum_transform_size(len: usize) -> Result<usize, String> {
+    let size = len
+        .checked_mul(2)
+        .and_then(|double| double.checked_next_power_of_two())
+        .filter(|&size| size <= NTT_MAX_SIZE);
+    size.ok_or_else(|| {
+        format!(
+            "Input too long for the spectrum: {} digits, at most {} are supported",
+            len,
+            NTT_MAX_SIZE / 2
+        )
+    })
+}
+
+fn ntt(values: &mut [u64], invert: bool) {
+    let n = values.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j ^= bit;
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+    let mut len = 2;
+    while len <= n {
+        let mut root = pow_mod(NTT_ROOT, (NTT_MODULUS - 1) / len as u64);
+        if invert {
+            root = pow_mod(root, NTT_MODULUS - 2);
+        }
+        for chunk in values.chunks_mut(len) {
+            let (low, high) = chunk.split_at_mut(len / 2);
+            let mut w = 1;
+            for (l, h) in low.iter_mut().zip(high.iter_mut()) {
+                let u = *l;
+                let v = *h * w % NTT_MODULUS;
+                *l = (u + v) % NTT_MODULUS;
+                *h = (u + NTT_MODULUS - v) % NTT_MODULUS;
+                w = w * root % NTT_MODULUS;
+            }
+        }
+        len <<= 1;
+    }
+    if invert {
+        let n_inv = pow_mod(n as u64, NTT_MODULUS - 2);
+        for value in values.iter_mut() {
+            *value = *value * n_inv % NTT_MODULUS;
+        }
+    }
+}
+
+fn pow_mod(mut base: u64, mut exp: u64) -> u64 {
+    let mut result = 1;
+    base %= NTT_MODULUS;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % NTT_MODULUS;
+        }
+        base = base * base % NTT_MODULUS;
+        exp >>= 1;
+    }
+    result
+}
+
+fn format_spectrum_table(spectrum: &[u32]) -> String {
+    let step_width = spectrum.len().to_string().len().max(4);
+    let sum_width = spectrum
+        .iter()
+        .map(|sum| sum.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+    let mut output = format!(
+        "{:>sw$} | {:>uw$}\n",
+        "step",
+        "sum",
+        sw = step_width,
+        uw = sum_width
+    );
+    output.push_str(&format!(
+        "{}-+-{}",
+        "-".repeat(step_width),
+        "-".repeat(sum_width)
+    ));
+    for (step, sum) in (1..).zip(spectrum) {
+        output.push_str(&format!(
+            "\n{:>sw$} | {:>uw$}",
+            step,
+            sum,
+            sw = step_width,
+            uw = sum_width
+        ));
+    }
+    output
+}
+
+fn format_spectrum_csv(spectrum: &[u32]) -> String {
+    let mut output = "step,sum".to_owned();
+    for (step, sum) in (1..).zip(spectrum) {
+        output.push_str(&format!("\n{},{}", step, sum));
+    }
+    output
+}
+
+fn calc_sum_streaming<R: Read>(reader: &mut R, step: usize, radix: u32) -> std::io::Result<u64> {
+    let mut stream = CaptchaStream::new(step);
+    for_each_digit(reader, radix, |digit| stream.push(digit))?;
+    Ok(stream.finish())
+}
+
+fn calc_sum_part2_streaming<R: Read + Seek>(reader: &mut R, radix: u32) -> std::io::Result<u64> {
+    let start = reader.stream_position()?;
+    let mut len: usize = 0;
+    for_each_digit(reader, radix, |_| len += 1)?;
+    reader.seek(SeekFrom::Start(start))?;
+    calc_sum_streaming(reader, len / 2, radix)
+}
+
+// Digits of any radix are ASCII, so the input does not have to be decoded: bytes of multi-byte
+// UTF-8 sequences are never valid digits.
+fn for_each_digit<R: Read, F: FnMut(u8)>(
+    reader: &mut R,
+    radix: u32,
+    mut f: F,
+) -> std::io::Result<()> {
+    let mut buffer = [0u8; CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(ref err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        for &byte in &buffer[..read] {
+            if let Some(digit) = (byte as char).to_digit(radix) {
+                f(digit as u8);
+            }
+        }
+    }
+}
+
+// Keeps the first `step` digits for the wrap-around at the end and a ring buffer of the
+// last `step` digits, so memory does not depend on the length of the input. The sum is a u64
+// as a multi-GB input easily exceeds a u32.
+struct CaptchaStream {
+    step: usize,
+    head: Vec<u8>,
+    ring: Vec<u8>,
+    len: usize,
+    sum: u64,
+}
+
+impl CaptchaStream {
+    fn new(step: usize) -> CaptchaStream {
+        CaptchaStream {
+            step,
+            head: Vec::with_capacity(step.min(CHUNK_SIZE)),
+            ring: Vec::with_capacity(step.min(CHUNK_SIZE)),
+            len: 0,
+            sum: 0,
+        }
+    }
+
+    fn push(&mut self, digit: u8) {
+        if self.step == 0 {
+            self.sum += digit as u64;
+        } else {
+            if self.head.len() < self.step {
+                self.head.push(digit);
+            }
+            let slot = self.len % self.step;
+            if self.ring.len() < self.step {
+                self.ring.push(digit);
+            } else {
+                if self.ring[slot] == digit {
+                    self.sum += digit as u64;
+                }
+                self.ring[slot] = digit;
+            }
+        }
+        self.len += 1;
+    }
+
+    fn finish(self) -> u64 {
+        if self.len < 2 {
+            return 0;
+        }
+        if self.step >= self.len {
+            // the whole input fits into the head, so the offset has to wrap around
+            return calc_sum(&self.head, self.step) as u64;
+        }
+        if self.step == 0 {
+            return self.sum;
+        }
+        // the last `step` digits still have to be compared with the first `step` digits
+        let mut sum = self.sum;
+        for i in 0..self.step {
+            let digit = self.ring[(self.len + i) % self.step];
+            if digit == self.head[i] {
+                sum += digit as u64;
+            }
+        }
+        sum
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
 
     #[test]
+    #[allow(clippy::needless_borrow)]
     fn calc_cum_calculates_correctly() {
         // given
         // some sample arrays with correct sums
@@ -83,6 +502,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_borrow)]
     fn calc_cum_part2_calculates_correctly() {
         // given
         // some sample arrays with correct sums
@@ -112,4 +532,278 @@ mod tests {
         // then
         assert_eq!(output, [1, 2, 3, 4, 4]);
     }
+
+    #[test]
+    fn calc_sum_streaming_matches_calc_sum() {
+        // given
+        let inputs = &[
+            "",
+            "8",
+            "1122",
+            "1111",
+            "1234",
+            "91212129",
+            "1212",
+            "123425",
+            "12131415",
+            "3x1\n41ö5926535 89793",
+        ];
+
+        // when/then
+        for input in inputs {
+            let digits = parse_radix(input, 10);
+            for step in 0..digits.len() + 3 {
+                let result = calc_sum_streaming(&mut input.as_bytes(), step, 10).unwrap();
+                assert_eq!(
+                    result,
+                    calc_sum(&digits, step) as u64,
+                    "input {}, step {}",
+                    input,
+                    step
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn calc_sum_part2_streaming_matches_calc_sum_part2() {
+        // given
+        let inputs = &[
+            "", "8", "1212", "1221", "123425", "123123", "12131415", "1x2y1z",
+        ];
+
+        // when/then
+        for input in inputs {
+            let mut cursor = Cursor::new(input.as_bytes());
+            let result = calc_sum_part2_streaming(&mut cursor, 10).unwrap();
+            assert_eq!(
+                result,
+                calc_sum_part2(&parse_radix(input, 10)) as u64,
+                "input {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn captcha_stream_sums_beyond_u32() {
+        // given
+        // a stream that already summed up u32::MAX, as after about 477M matching nines
+        let mut stream = CaptchaStream::new(1);
+        for _ in 0..3 {
+            stream.push(9);
+        }
+        stream.sum = u32::MAX as u64;
+
+        // when
+        stream.push(9);
+        let sum = stream.finish();
+
+        // then
+        assert_eq!(sum, u32::MAX as u64 + 9 + 9);
+    }
+
+    #[test]
+    fn calc_sum_streaming_works_across_chunk_boundaries() {
+        // given
+        let input: String = (0..3 * CHUNK_SIZE + 17)
+            .map(|i| (b'0' + ((i * 7 + i / 3) % 10) as u8) as char)
+            .collect();
+        let digits = parse_radix(&input, 10);
+
+        // when
+        let result = calc_sum_streaming(&mut input.as_bytes(), 1000, 10).unwrap();
+
+        // then
+        assert_eq!(result, calc_sum(&digits, 1000) as u64);
+    }
+
+    #[test]
+    fn calc_sum_spectrum_matches_calc_sum_for_each_step() {
+        // given
+        let inputs: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![8],
+            vec![1, 1],
+            vec![1, 2, 1, 2],
+            vec![9, 1, 2, 1, 2, 1, 2, 9],
+            vec![1, 2, 3, 4, 2, 5, 0, 0, 7],
+            (0..1000).map(|i| ((i * i + 3 * i) % 7) as u8).collect(),
+            (0..777).map(|i| ((i * 31 + i / 5) % 10) as u8).collect(),
+        ];
+
+        // when/then
+        for digits in &inputs {
+            let spectrum = calc_sum_spectrum(digits).unwrap();
+            assert_eq!(spectrum.len(), digits.len() / 2);
+            for (step, &sum) in (1..).zip(spectrum.iter()) {
+                assert_eq!(
+                    sum,
+                    calc_sum(digits, step),
+                    "digits {:?}, step {}",
+                    digits,
+                    step
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn spectrum_transform_size_is_limited_by_the_modulus() {
+        // when/then
+        assert_eq!(spectrum_transform_size(3), Ok(8));
+        assert_eq!(spectrum_transform_size(5_000_000), Ok(1 << 24));
+        assert_eq!(spectrum_transform_size(1 << 26), Ok(1 << 27));
+        assert!(spectrum_transform_size((1 << 26) + 1).is_err());
+        assert!(spectrum_transform_size(usize::MAX).is_err());
+        // the root has order exactly NTT_MAX_SIZE, so every supported size has its roots
+        let root = pow_mod(NTT_ROOT, (NTT_MODULUS - 1) / NTT_MAX_SIZE as u64);
+        assert_eq!(pow_mod(root, NTT_MAX_SIZE as u64 / 2), NTT_MODULUS - 1);
+    }
+
+    #[test]
+    fn format_spectrum_csv_lists_step_and_sum() {
+        // given
+        let spectrum = calc_sum_spectrum(&[1, 2, 1, 2]).unwrap();
+
+        // when
+        let csv = format_spectrum_csv(&spectrum);
+
+        // then
+        assert_eq!(csv, "step,sum\n1,0\n2,6");
+    }
+
+    #[test]
+    fn parse_radix_reads_wider_digits() {
+        // given
+        let input = "fF0a-9z";
+
+        // when
+        let hex = parse_radix(input, 16);
+        let base36 = parse_radix(input, 36);
+
+        // then
+        assert_eq!(hex, [15, 15, 0, 10, 9]);
+        assert_eq!(base36, [15, 15, 0, 10, 9, 35]);
+    }
+
+    #[test]
+    fn calc_sum_works_on_hex_digits() {
+        // given
+        let digits = parse_radix("ff1ab", 16);
+
+        // when
+        let sum = calc_sum(&digits, 1);
+
+        // then
+        assert_eq!(sum, 15);
+    }
+
+    #[test]
+    fn parse_strict_accepts_valid_input() {
+        // given
+        let input = "12 34\n56\n";
+
+        // when
+        let result = parse_strict(input, 10);
+
+        // then
+        assert_eq!(result, Ok(vec![1, 2, 3, 4, 5, 6]));
+    }
+
+    #[test]
+    fn parse_strict_reports_every_invalid_character() {
+        // given
+        let input = "12ö34\n r4";
+
+        // when
+        let result = parse_strict(input, 10);
+
+        // then
+        assert_eq!(
+            result,
+            Err(vec![
+                InvalidChar {
+                    character: 'ö',
+                    line: 1,
+                    column: 3,
+                    offset: 2,
+                },
+                InvalidChar {
+                    character: 'r',
+                    line: 2,
+                    column: 2,
+                    offset: 8,
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_strict_ignores_whitespace() {
+        // given
+        let input = " \t1\r\n2 \u{a0}3\n\n";
+
+        // when
+        let result = parse_strict(input, 10);
+
+        // then
+        assert_eq!(result, Ok(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn calc_sum_streaming_respects_radix() {
+        // given
+        let input = "zz1zx";
+
+        // when
+        let sum = calc_sum_streaming(&mut input.as_bytes(), 1, 36).unwrap();
+
+        // then
+        assert_eq!(sum, calc_sum(&parse_radix(input, 36), 1) as u64);
+    }
+
+    #[test]
+    fn parse_options_reads_modes_and_flags() {
+        // given
+        let args: Vec<String> = ["--radix", "16", "--spectrum", "csv", "--strict"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        // when
+        let options = parse_options(&args);
+
+        // then
+        assert_eq!(
+            options,
+            Ok(Options {
+                mode: Mode::Spectrum(SpectrumFormat::Csv),
+                radix: 16,
+                strict: true,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_options_rejects_invalid_values() {
+        // given
+        let to_args = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+
+        // when/then
+        assert_eq!(
+            parse_options(&to_args(&["--stream", "--radix", "16"])).map(|o| o.mode),
+            Ok(Mode::Stream(None))
+        );
+        assert_eq!(
+            parse_options(&to_args(&["--stream", "7"])).map(|o| o.mode),
+            Ok(Mode::Stream(Some(7)))
+        );
+        assert!(parse_options(&to_args(&["--stream", "abc"]))
+            .unwrap_err()
+            .starts_with("Invalid step 'abc'"));
+        assert!(parse_options(&to_args(&["--spectrum", "xml"]))
+            .unwrap_err()
+            .starts_with("Unknown spectrum format 'xml'"));
+    }
 }
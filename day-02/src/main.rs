@@ -2,7 +2,10 @@ use std::io::Read;
 use std::path::Path;
 use std::fs::File;
 use std::env;
+use std::collections::BTreeMap;
+use std::fmt;
 
+#[allow(clippy::needless_return)]
 fn main() {
     let args: Vec<String> = env::args().collect();
     match args.get(1) {
@@ -11,69 +14,868 @@ fn main() {
             return;
         }
         Some(filename) => {
-            let input = read_and_parse(&Path::new(filename));
-            match input {
-                Ok(rows) => {
-                    println!("Checksum is: {}", division_checksum(&rows));
-                }
-                Err(err) => {
-                    println!("Unable to read input: {}", err);
+            if let Err(err) = run(Path::new(filename), &args[2..]) {
+                println!("{}", err);
+            }
+        }
+    }
+}
+
+enum Action {
+    Checksum,
+    DivisionChecksum,
+    Metrics(Vec<Box<dyn RowMetric>>),
+    Pairs,
+}
+
+fn run(path: &Path, args: &[String]) -> Result<(), String> {
+    let (action, table_options) = parse_options(args)?;
+    let input = read_input(path).map_err(|err| format!("Unable to read input: {}", err))?;
+    let table = if table_options == TableOptions::lenient() {
+        Table {
+            first_line: 1,
+            rows: parse_lines(&input),
+        }
+    } else {
+        parse_table(&input, &table_options).map_err(|errors| {
+            errors
+                .iter()
+                .map(|err| err.to_string())
+                .collect::<Vec<String>>()
+                .join("\n")
+        })?
+    };
+    let Table { first_line, rows } = table;
+    // Everything is calculated before the first line is printed, so a failing row leaves no
+    // partial output behind.
+    let output: Vec<String> = match action {
+        Action::Checksum => vec![format!(
+            "Checksum is: {}",
+            checksum(&rows, first_line).map_err(|e| e.to_string())?
+        )],
+        Action::DivisionChecksum => vec![format!(
+            "Checksum is: {}",
+            division_checksum(&rows, first_line).map_err(|e| e.to_string())?
+        )],
+        Action::Metrics(metrics) => metrics
+            .iter()
+            .map(|metric| {
+                metric_checksum(metric.as_ref(), &rows, first_line)
+                    .map(|sum| format!("{} checksum is: {}", metric.name(), sum))
+                    .map_err(|e| e.to_string())
+            })
+            .collect::<Result<Vec<String>, String>>()?,
+        Action::Pairs => pairs_report(&rows, first_line),
+    };
+    for line in output {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn parse_options(args: &[String]) -> Result<(Action, TableOptions), String> {
+    let mut action = Action::DivisionChecksum;
+    let mut table_options = TableOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--minmax" => action = Action::Checksum,
+            "--metrics" => action = Action::Metrics(parse_metrics(args.next())?),
+            "--pairs" => action = Action::Pairs,
+            "--delimiter" => {
+                table_options.delimiter = match args.next().map(|s| s.as_str()) {
+                    Some("csv") => Delimiter::Char(','),
+                    Some("tsv") => Delimiter::Char('\t'),
+                    Some("whitespace") => Delimiter::Whitespace,
+                    Some(other) if other.chars().count() == 1 => {
+                        Delimiter::Char(other.chars().next().expect("expected one char"))
+                    }
+                    _ => {
+                        return Err(
+                            "Expected csv, tsv, whitespace or a single character as delimiter"
+                                .to_owned(),
+                        )
+                    }
                 }
             }
+            "--header" => table_options.has_header = true,
+            "--columns" => {
+                let columns = args.next().ok_or_else(|| "No columns given".to_owned())?;
+                table_options.columns = Some(parse_columns(columns));
+            }
+            "--lenient" => table_options.lenient = true,
+            option => return Err(format!("Unknown option '{}'", option)),
         }
     }
+    Ok((action, table_options))
 }
 
-fn read_and_parse(path: &Path) -> std::io::Result<Vec<Vec<i32>>> {
+fn read_input(path: &Path) -> std::io::Result<String> {
     let mut file = File::open(path)?;
     let mut input = String::with_capacity(1024);
     file.read_to_string(&mut input)?;
-    let input = input;
+    Ok(input)
+}
 
-    return Ok(parse_lines(&input));
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Delimiter {
+    Whitespace,
+    Char(char),
+}
+
+impl Delimiter {
+    fn matches(self, c: char) -> bool {
+        match self {
+            Delimiter::Whitespace => c.is_whitespace(),
+            Delimiter::Char(delimiter) => c == delimiter,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ColumnRef {
+    // 1-based, like the column numbers in error messages
+    Index(usize),
+    Name(String),
+}
+
+fn parse_columns(columns: &str) -> Vec<ColumnRef> {
+    columns
+        .split(',')
+        .map(|column| match column.trim().parse::<usize>() {
+            Ok(index) => ColumnRef::Index(index),
+            Err(_) => ColumnRef::Name(column.trim().to_owned()),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TableOptions {
+    delimiter: Delimiter,
+    has_header: bool,
+    columns: Option<Vec<ColumnRef>>,
+    lenient: bool,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        TableOptions {
+            delimiter: Delimiter::Whitespace,
+            has_header: false,
+            columns: None,
+            lenient: false,
+        }
+    }
+}
+
+impl TableOptions {
+    // the original input format: whitespace separated, bad cells are skipped
+    fn lenient() -> Self {
+        TableOptions {
+            lenient: true,
+            ..TableOptions::default()
+        }
+    }
+}
+
+// Rows are 1-based input line numbers, so a header line is counted as line 1 and the first data
+// row below it is row 2. Columns are 1-based and count all columns of the input, not only the
+// selected ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TableError {
+    InvalidCell {
+        row: usize,
+        column: usize,
+        content: String,
+    },
+    MissingColumn {
+        row: usize,
+        column: usize,
+    },
+    UnterminatedQuote {
+        row: usize,
+    },
+    UnknownColumn(String),
+    InvalidColumn(usize),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TableError::InvalidCell {
+                row,
+                column,
+                content,
+            } => write!(
+                f,
+                "Invalid number '{}' in row {}, column {}",
+                content, row, column
+            ),
+            TableError::MissingColumn { row, column } => {
+                write!(f, "Row {} has no column {}", row, column)
+            }
+            TableError::UnterminatedQuote { row } => {
+                write!(f, "Unterminated quote in row {}", row)
+            }
+            TableError::UnknownColumn(name) => write!(f, "Unknown column '{}'", name),
+            TableError::InvalidColumn(index) => {
+                write!(f, "Invalid column {}, columns start at 1", index)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Table {
+    // input line of the first row, counted like the rows of a TableError
+    first_line: usize,
+    rows: Vec<Vec<i32>>,
+}
+
+// Blank lines become empty rows, so the row at index i is on input line first_line + i.
+// In lenient mode, cells that are missing or no number are skipped instead of being reported.
+fn parse_table(input: &str, options: &TableOptions) -> Result<Table, Vec<TableError>> {
+    let mut lines = input.split('\n').enumerate();
+    let mut header: Vec<String> = Vec::new();
+    let mut first_line = 1;
+    if options.has_header {
+        if let Some((_, line)) = lines.next() {
+            header = split_fields(line, options.delimiter).0;
+            first_line = 2;
+        }
+    }
+    let columns: Option<Vec<usize>> = match options.columns {
+        None => None,
+        Some(ref columns) => Some(resolve_columns(columns, &header).map_err(|err| vec![err])?),
+    };
+
+    let mut rows: Vec<Vec<i32>> = Vec::new();
+    let mut errors: Vec<TableError> = Vec::new();
+    for (i, line) in lines {
+        let row_number = i + 1;
+        if line.trim().is_empty() {
+            rows.push(Vec::new());
+            continue;
+        }
+        let (fields, unterminated) = split_fields(line, options.delimiter);
+        if unterminated && !options.lenient {
+            errors.push(TableError::UnterminatedQuote { row: row_number });
+        }
+        let selected: Vec<(usize, Option<&String>)> = match columns {
+            None => fields
+                .iter()
+                .enumerate()
+                .map(|(i, f)| (i, Some(f)))
+                .collect(),
+            Some(ref columns) => columns.iter().map(|&i| (i, fields.get(i))).collect(),
+        };
+        let mut row: Vec<i32> = Vec::with_capacity(selected.len());
+        for (column, field) in selected {
+            match field.map(|f| (f, f.parse::<i32>())) {
+                Some((_, Ok(value))) => row.push(value),
+                _ if options.lenient => {}
+                Some((content, Err(_))) => errors.push(TableError::InvalidCell {
+                    row: row_number,
+                    column: column + 1,
+                    content: content.clone(),
+                }),
+                None => errors.push(TableError::MissingColumn {
+                    row: row_number,
+                    column: column + 1,
+                }),
+            }
+        }
+        rows.push(row);
+    }
+    if errors.is_empty() {
+        Ok(Table { first_line, rows })
+    } else {
+        Err(errors)
+    }
 }
 
+#[allow(clippy::needless_return)]
 fn parse_lines(input: &str) -> Vec<Vec<i32>> {
     return input.split("\n").map(line_to_row).collect();
 }
 
 fn line_to_row(line: &str) -> Vec<i32> {
-    return line.split_whitespace()
-        .filter_map(|numstr| numstr.parse::<i32>().ok())
-        .collect();
+    // A lenient table without column selection reports no errors.
+    parse_table(line, &TableOptions::lenient())
+        .ok()
+        .and_then(|mut table| table.rows.pop())
+        .unwrap_or_default()
+}
+
+fn resolve_columns(columns: &[ColumnRef], header: &[String]) -> Result<Vec<usize>, TableError> {
+    columns
+        .iter()
+        .map(|column| match *column {
+            ColumnRef::Index(0) => Err(TableError::InvalidColumn(0)),
+            ColumnRef::Index(index) => Ok(index - 1),
+            ColumnRef::Name(ref name) => header
+                .iter()
+                .position(|h| h == name)
+                .ok_or_else(|| TableError::UnknownColumn(name.clone())),
+        })
+        .collect()
+}
+
+// Splits a line into trimmed fields. Fields may be quoted with '"', a doubled quote inside a
+// quoted field is a literal quote. The flag is set if the last quote was not closed.
+fn split_fields(line: &str, delimiter: Delimiter) -> (Vec<String>, bool) {
+    let mut fields: Vec<String> = Vec::new();
+    let mut unterminated = false;
+    let mut chars = line.trim_end_matches('\r').chars().peekable();
+    loop {
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() && (delimiter == Delimiter::Whitespace || !delimiter.matches(c)) {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        if delimiter == Delimiter::Whitespace && chars.peek().is_none() {
+            break;
+        }
+        let mut field = String::new();
+        if chars.peek() == Some(&'"') {
+            chars.next();
+            loop {
+                match chars.next() {
+                    None => {
+                        unterminated = true;
+                        break;
+                    }
+                    Some('"') if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    Some('"') => break,
+                    Some(c) => field.push(c),
+                }
+            }
+        }
+        while let Some(&c) = chars.peek() {
+            if delimiter.matches(c) {
+                break;
+            }
+            field.push(c);
+            chars.next();
+        }
+        fields.push(field.trim_end().to_owned());
+        if chars.next().is_none() {
+            break;
+        }
+    }
+    (fields, unterminated)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RowError {
+    Overflow,
+    // 1-based column of the second zero cell
+    ZeroDivisor(usize),
+}
+
+// `row` is the input line of the row, counted like the rows of a TableError
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ChecksumError {
+    row: usize,
+    error: RowError,
+}
+
+impl fmt::Display for ChecksumError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.error {
+            RowError::Overflow => write!(f, "Checksum overflows in row {}", self.row),
+            RowError::ZeroDivisor(column) => write!(
+                f,
+                "Row {} has a second zero in column {}, and zero divided by zero has no quotient",
+                self.row, column
+            ),
+        }
+    }
+}
+
+// `first_line` is the input line of the first row, as given by the parsed Table
+fn sum_rows<F>(rows: &[Vec<i32>], first_line: usize, row_value: F) -> Result<i64, ChecksumError>
+where
+    F: Fn(&[i32]) -> Result<i64, RowError>,
+{
+    let mut sum: i64 = 0;
+    for (i, row) in rows.iter().enumerate() {
+        let error = |error| ChecksumError {
+            row: first_line + i,
+            error,
+        };
+        let value = row_value(row).map_err(error)?;
+        sum = sum
+            .checked_add(value)
+            .ok_or_else(|| error(RowError::Overflow))?;
+    }
+    Ok(sum)
 }
 
-fn checksum(rows: &Vec<Vec<i32>>) -> i32 {
-    return rows.iter().map(|row| row_sum(&row)).sum();
+fn checksum(rows: &[Vec<i32>], first_line: usize) -> Result<i64, ChecksumError> {
+    sum_rows(rows, first_line, |row| Ok(row_sum(row)))
 }
 
-fn row_sum(row: &[i32]) -> i32 {
-    let min = row.iter().min().unwrap_or(&0);
-    let max = row.iter().max().unwrap_or(&0);
+fn row_sum(row: &[i32]) -> i64 {
+    let min = row.iter().min().cloned().unwrap_or(0) as i64;
+    let max = row.iter().max().cloned().unwrap_or(0) as i64;
 
-    return max - min;
+    max - min
 }
 
-fn even_divide_row(row: &[i32]) -> i32 {
+// Zero is a multiple of every other cell, so 0 / x is an even division with quotient 0, but zero
+// divides no cell. Only a second zero is rejected, as zero divided by zero has no quotient.
+fn even_divide_row(row: &[i32]) -> Result<i64, RowError> {
+    if let Some(column) = row
+        .iter()
+        .enumerate()
+        .filter(|&(_, &v)| v == 0)
+        .nth(1)
+        .map(|(i, _)| i)
+    {
+        return Err(RowError::ZeroDivisor(column + 1));
+    }
+    let row: Vec<i64> = row.iter().map(|&v| v as i64).collect();
     let len = row.len();
     if len < 2 {
-        return 0;
+        return Ok(0);
     }
     for i in 0..(len - 1) {
         for j in i + 1..len {
-            if row[i] % row[j] == 0 {
-                return row[i] / row[j];
+            if row[j] != 0 && row[i] % row[j] == 0 {
+                return Ok(row[i] / row[j]);
+            }
+            if row[i] != 0 && row[j] % row[i] == 0 {
+                return Ok(row[j] / row[i]);
+            }
+        }
+    }
+    Ok(0)
+}
+
+fn division_checksum(rows: &[Vec<i32>], first_line: usize) -> Result<i64, ChecksumError> {
+    sum_rows(rows, first_line, even_divide_row)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MetricValue {
+    Integer(i64),
+    Real(f64),
+}
+
+impl MetricValue {
+    fn checked_add(self, other: MetricValue) -> Option<MetricValue> {
+        match (self, other) {
+            (MetricValue::Integer(l), MetricValue::Integer(r)) => {
+                l.checked_add(r).map(MetricValue::Integer)
+            }
+            (l, r) => Some(MetricValue::Real(l.as_real() + r.as_real())),
+        }
+    }
+
+    fn as_real(self) -> f64 {
+        match self {
+            MetricValue::Integer(value) => value as f64,
+            MetricValue::Real(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MetricValue::Integer(value) => write!(f, "{}", value),
+            MetricValue::Real(value) => write!(f, "{:.3}", value),
+        }
+    }
+}
+
+trait RowMetric {
+    fn name(&self) -> &'static str;
+    fn row_value(&self, row: &[i32]) -> Result<MetricValue, RowError>;
+}
+
+const METRIC_NAMES: [&str; 6] = ["minmax", "division", "median", "stddev", "primes", "gcd"];
+
+fn metric_by_name(name: &str) -> Option<Box<dyn RowMetric>> {
+    match name {
+        "minmax" => Some(Box::new(MinMaxSpread)),
+        "division" => Some(Box::new(EvenDivision)),
+        "median" => Some(Box::new(MedianSpread)),
+        "stddev" => Some(Box::new(StandardDeviation)),
+        "primes" => Some(Box::new(PrimeCount)),
+        "gcd" => Some(Box::new(GcdSum)),
+        _ => None,
+    }
+}
+
+fn parse_metrics(names: Option<&String>) -> Result<Vec<Box<dyn RowMetric>>, String> {
+    let names = names.ok_or_else(|| {
+        format!(
+            "No metrics given, available metrics are: {}",
+            METRIC_NAMES.join(",")
+        )
+    })?;
+    names
+        .split(',')
+        .map(|name| {
+            metric_by_name(name.trim()).ok_or_else(|| {
+                format!(
+                    "Unknown metric '{}', available metrics are: {}",
+                    name,
+                    METRIC_NAMES.join(",")
+                )
+            })
+        })
+        .collect()
+}
+
+fn metric_checksum(
+    metric: &dyn RowMetric,
+    rows: &[Vec<i32>],
+    first_line: usize,
+) -> Result<MetricValue, ChecksumError> {
+    let mut sum = MetricValue::Integer(0);
+    for (i, row) in rows.iter().enumerate() {
+        let error = |error| ChecksumError {
+            row: first_line + i,
+            error,
+        };
+        let value = metric.row_value(row).map_err(error)?;
+        sum = sum
+            .checked_add(value)
+            .ok_or_else(|| error(RowError::Overflow))?;
+    }
+    Ok(sum)
+}
+
+struct MinMaxSpread;
+
+impl RowMetric for MinMaxSpread {
+    fn name(&self) -> &'static str {
+        "minmax"
+    }
+
+    fn row_value(&self, row: &[i32]) -> Result<MetricValue, RowError> {
+        Ok(MetricValue::Integer(row_sum(row)))
+    }
+}
+
+struct EvenDivision;
+
+impl RowMetric for EvenDivision {
+    fn name(&self) -> &'static str {
+        "division"
+    }
+
+    fn row_value(&self, row: &[i32]) -> Result<MetricValue, RowError> {
+        even_divide_row(row).map(MetricValue::Integer)
+    }
+}
+
+// median absolute deviation from the row's median
+struct MedianSpread;
+
+impl RowMetric for MedianSpread {
+    fn name(&self) -> &'static str {
+        "median"
+    }
+
+    fn row_value(&self, row: &[i32]) -> Result<MetricValue, RowError> {
+        let values: Vec<f64> = row.iter().map(|&v| v as f64).collect();
+        let center = median(values.clone());
+        Ok(MetricValue::Real(median(
+            values.iter().map(|v| (v - center).abs()).collect(),
+        )))
+    }
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.sort_by(|l, r| l.partial_cmp(r).expect("expected values to be comparable"));
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / 2.0
+    } else {
+        values[middle]
+    }
+}
+
+// population standard deviation
+struct StandardDeviation;
+
+impl RowMetric for StandardDeviation {
+    fn name(&self) -> &'static str {
+        "stddev"
+    }
+
+    fn row_value(&self, row: &[i32]) -> Result<MetricValue, RowError> {
+        if row.is_empty() {
+            return Ok(MetricValue::Real(0.0));
+        }
+        let n = row.len() as f64;
+        let mean = row.iter().map(|&v| v as f64).sum::<f64>() / n;
+        let variance = row
+            .iter()
+            .map(|&v| (v as f64 - mean) * (v as f64 - mean))
+            .sum::<f64>()
+            / n;
+        Ok(MetricValue::Real(variance.sqrt()))
+    }
+}
+
+struct PrimeCount;
+
+impl RowMetric for PrimeCount {
+    fn name(&self) -> &'static str {
+        "primes"
+    }
+
+    fn row_value(&self, row: &[i32]) -> Result<MetricValue, RowError> {
+        Ok(MetricValue::Integer(
+            row.iter().filter(|&&v| is_prime(v)).count() as i64,
+        ))
+    }
+}
+
+// Deterministic Miller-Rabin, the bases 2, 7 and 61 are sufficient for all 32 bit numbers.
+fn is_prime(value: i32) -> bool {
+    if value < 2 {
+        return false;
+    }
+    let n = value as u64;
+    for p in [2, 3, 5, 7, 11, 13, 61] {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let mut d = n - 1;
+    let mut r = 0;
+    while d.is_multiple_of(2) {
+        d /= 2;
+        r += 1;
+    }
+    'witness: for a in [2, 7, 61] {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..r {
+            x = x * x % n;
+            if x == n - 1 {
+                continue 'witness;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let mut result = 1;
+    base %= modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = result * base % modulus;
+        }
+        base = base * base % modulus;
+        exp >>= 1;
+    }
+    result
+}
+
+// sum of the GCDs of all pairs of cells in the row
+struct GcdSum;
+
+impl RowMetric for GcdSum {
+    fn name(&self) -> &'static str {
+        "gcd"
+    }
+
+    fn row_value(&self, row: &[i32]) -> Result<MetricValue, RowError> {
+        let mut sum: i64 = 0;
+        for (i, &l) in row.iter().enumerate() {
+            for &r in &row[i + 1..] {
+                sum = sum
+                    .checked_add(gcd(l.unsigned_abs(), r.unsigned_abs()) as i64)
+                    .ok_or(RowError::Overflow)?;
             }
-            if row[j] % row[i] == 0 {
-                return row[j] / row[i];
+        }
+        Ok(MetricValue::Integer(sum))
+    }
+}
+
+fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let t = a % b;
+        a = b;
+        b = t;
+    }
+    a
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct DivisiblePair {
+    dividend: i32,
+    divisor: i32,
+    quotient: i64,
+    // number of cell pairs in the row with these values
+    count: usize,
+}
+
+impl fmt::Display for DivisiblePair {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} / {} = {}",
+            self.dividend, self.divisor, self.quotient
+        )?;
+        if self.count > 1 {
+            write!(f, " (x{})", self.count)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RowDivisibility {
+    NoPair,
+    Unique(DivisiblePair),
+    Several(Vec<DivisiblePair>),
+}
+
+impl fmt::Display for RowDivisibility {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RowDivisibility::NoPair => write!(f, "no evenly divisible pair"),
+            RowDivisibility::Unique(pair) => write!(f, "{}", pair),
+            RowDivisibility::Several(pairs) => {
+                let count: usize = pairs.iter().map(|pair| pair.count).sum();
+                write!(f, "{} evenly divisible pairs: ", count)?;
+                for (i, pair) in pairs.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", pair)?;
+                }
+                Ok(())
             }
         }
     }
-    return 0;
 }
 
-fn division_checksum(rows: &[Vec<i32>]) -> i32 {
-    return rows.iter().map(|row| even_divide_row(&row)).sum();
+// One line per row, leaving out blank lines such as the one after a trailing newline.
+fn pairs_report(rows: &[Vec<i32>], first_line: usize) -> Vec<String> {
+    rows.iter()
+        .enumerate()
+        .filter(|(_, row)| !row.is_empty())
+        .map(|(i, row)| format!("row {}: {}", first_line + i, classify_row(row)))
+        .collect()
+}
+
+fn classify_row(row: &[i32]) -> RowDivisibility {
+    let mut pairs = divisible_pairs(row);
+    match pairs.len() {
+        0 => RowDivisibility::NoPair,
+        1 if pairs[0].count == 1 => RowDivisibility::Unique(pairs.remove(0)),
+        _ => RowDivisibility::Several(pairs),
+    }
+}
+
+// Lists every pair of cells where one cell evenly divides the other, with the larger absolute
+// value as dividend. Cells with equal values are merged into one entry with a count.
+// Zero can be a dividend, but never a divisor.
+fn divisible_pairs(row: &[i32]) -> Vec<DivisiblePair> {
+    let mut counts: BTreeMap<i32, usize> = BTreeMap::new();
+    for &value in row {
+        *counts.entry(value).or_insert(0) += 1;
+    }
+    let zeros = counts.remove(&0).unwrap_or(0);
+
+    let mut by_abs: BTreeMap<u32, Vec<(i32, usize)>> = BTreeMap::new();
+    for (&value, &count) in &counts {
+        by_abs
+            .entry(value.unsigned_abs())
+            .or_default()
+            .push((value, count));
+    }
+    let abs_values: Vec<u32> = by_abs.keys().cloned().collect();
+    let max_abs = abs_values.last().cloned().unwrap_or(0) as u64;
+
+    let mut pairs: Vec<DivisiblePair> = Vec::new();
+    for (index, &divisor_abs) in abs_values.iter().enumerate() {
+        let divisors = &by_abs[&divisor_abs];
+        if zeros > 0 {
+            for &(divisor, count) in divisors {
+                pairs.push(DivisiblePair {
+                    dividend: 0,
+                    divisor,
+                    quotient: 0,
+                    count: zeros * count,
+                });
+            }
+        }
+        pairs.extend(equal_abs_pairs(divisors));
+
+        // Probe the multiples of the divisor if there are fewer of them than larger cells,
+        // otherwise test every larger cell.
+        let larger = &abs_values[index + 1..];
+        let multiples: Vec<u32> = if max_abs / (divisor_abs as u64) < larger.len() as u64 {
+            (2..=max_abs / divisor_abs as u64)
+                .map(|k| (k * divisor_abs as u64) as u32)
+                .filter(|multiple| by_abs.contains_key(multiple))
+                .collect()
+        } else {
+            larger
+                .iter()
+                .cloned()
+                .filter(|value| value % divisor_abs == 0)
+                .collect()
+        };
+        for multiple in multiples {
+            for &(dividend, dividend_count) in &by_abs[&multiple] {
+                for &(divisor, divisor_count) in divisors {
+                    pairs.push(DivisiblePair {
+                        dividend,
+                        divisor,
+                        quotient: dividend as i64 / divisor as i64,
+                        count: dividend_count * divisor_count,
+                    });
+                }
+            }
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+fn equal_abs_pairs(values: &[(i32, usize)]) -> Vec<DivisiblePair> {
+    let mut pairs: Vec<DivisiblePair> = values
+        .iter()
+        .filter(|&&(_, count)| count > 1)
+        .map(|&(value, count)| DivisiblePair {
+            dividend: value,
+            divisor: value,
+            quotient: 1,
+            count: count * (count - 1) / 2,
+        })
+        .collect();
+    if let [(negative, negative_count), (positive, positive_count)] = *values {
+        pairs.push(DivisiblePair {
+            dividend: positive,
+            divisor: negative,
+            quotient: -1,
+            count: positive_count * negative_count,
+        });
+    }
+    pairs
 }
 
 #[cfg(test)]
@@ -87,10 +889,10 @@ mod tests {
 
         // when
         let rows = parse_lines(input);
-        let result = checksum(&rows);
+        let result = checksum(&rows, 1);
 
         // then
-        assert_eq!(result, 18);
+        assert_eq!(result, Ok(18));
     }
 
     #[test]
@@ -106,6 +908,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_borrow)]
     fn line_to_row_should_parse_line() {
         // given
         let line = "1\tlöo   123  89 ABC .w093re";
@@ -117,10 +920,181 @@ mod tests {
         assert_eq!(result, &[1, 123, 89]);
     }
 
+    #[test]
+    fn parse_table_should_parse_lines() {
+        // given
+        let line = "1 2 3\n 4 5 \n 42";
+
+        // when
+        let result = parse_table(line, &TableOptions::default());
+
+        // then
+        assert_eq!(
+            result.map(|table| table.rows),
+            Ok(vec![vec![1, 2, 3], vec![4, 5], vec![42]])
+        )
+    }
+
+    #[test]
+    fn parse_table_should_skip_bad_cells_when_lenient() {
+        // given
+        let line = "1\tlöo   123  89 ABC .w093re";
+
+        // when
+        let result = parse_table(line, &TableOptions::lenient());
+
+        // then
+        assert_eq!(result.map(|table| table.rows), Ok(vec![vec![1, 123, 89]]));
+    }
+
+    #[test]
+    fn parse_table_should_report_bad_cells() {
+        // given
+        let input = "1 2 3\n4 12a 6 x";
+
+        // when
+        let result = parse_table(input, &TableOptions::default());
+
+        // then
+        assert_eq!(
+            result,
+            Err(vec![
+                TableError::InvalidCell {
+                    row: 2,
+                    column: 2,
+                    content: "12a".to_owned(),
+                },
+                TableError::InvalidCell {
+                    row: 2,
+                    column: 4,
+                    content: "x".to_owned(),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn parse_table_should_read_csv_with_header_and_quotes() {
+        // given
+        let input = "id, \"a, b\",c\n1, \"2\" ,3\n4,5,\"6\"\n";
+        let options = TableOptions {
+            delimiter: Delimiter::Char(','),
+            has_header: true,
+            ..TableOptions::default()
+        };
+
+        // when
+        let result = parse_table(input, &options);
+
+        // then
+        assert_eq!(
+            result,
+            Ok(Table {
+                first_line: 2,
+                rows: vec![vec![1, 2, 3], vec![4, 5, 6], vec![]],
+            })
+        );
+    }
+
+    #[test]
+    fn parse_table_should_select_columns_by_index_and_name() {
+        // given
+        let input = "id\tx\ty\n1\t2\t3\n4\t5\t6";
+        let options = TableOptions {
+            delimiter: Delimiter::Char('\t'),
+            has_header: true,
+            columns: Some(parse_columns("y,2")),
+            lenient: false,
+        };
+
+        // when
+        let result = parse_table(input, &options);
+
+        // then
+        assert_eq!(
+            result.map(|table| table.rows),
+            Ok(vec![vec![3, 2], vec![6, 5]])
+        );
+    }
+
+    #[test]
+    fn parse_table_should_report_missing_and_unknown_columns() {
+        // given
+        let input = "a,b\n1,2\n3";
+        let mut options = TableOptions {
+            delimiter: Delimiter::Char(','),
+            has_header: true,
+            columns: Some(parse_columns("b")),
+            lenient: false,
+        };
+
+        // when
+        let missing = parse_table(input, &options);
+        options.columns = Some(parse_columns("c"));
+        let unknown = parse_table(input, &options);
+
+        // then
+        assert_eq!(
+            missing,
+            Err(vec![TableError::MissingColumn { row: 3, column: 2 }])
+        );
+        assert_eq!(
+            unknown,
+            Err(vec![TableError::UnknownColumn("c".to_owned())])
+        );
+    }
+
+    #[test]
+    fn parse_table_should_count_header_line_in_rows() {
+        // given
+        let input = "a b\n1 2\n3 x";
+        let options = TableOptions {
+            has_header: true,
+            ..TableOptions::default()
+        };
+
+        // when
+        let result = parse_table(input, &options);
+
+        // then
+        assert_eq!(
+            result,
+            Err(vec![TableError::InvalidCell {
+                row: 3,
+                column: 2,
+                content: "x".to_owned(),
+            }])
+        );
+    }
+
+    #[test]
+    fn split_fields_should_handle_quotes() {
+        // given
+        let test_data: &[(&str, Delimiter, Vec<&str>, bool)] = &[
+            ("1,,3", Delimiter::Char(','), vec!["1", "", "3"], false),
+            ("1,2,", Delimiter::Char(','), vec!["1", "2", ""], false),
+            (
+                "\"a \"\"b\"\"\" c",
+                Delimiter::Whitespace,
+                vec!["a \"b\"", "c"],
+                false,
+            ),
+            ("1\t\"2", Delimiter::Char('\t'), vec!["1", "2"], true),
+        ];
+
+        // when/then
+        for &(line, delimiter, ref fields, unterminated) in test_data {
+            assert_eq!(
+                split_fields(line, delimiter),
+                (fields.iter().map(|f| f.to_string()).collect(), unterminated)
+            );
+        }
+    }
+
     #[test]
     fn row_sum_should_calculate_max_minus_min() {
         // given
-        let test_data: [(Vec<i32>, i32); 8] = [
+        let test_data: [(Vec<i32>, i64); 8] = [
             (vec![], 0),
             (vec![4], 0),
             (vec![1, 2, 3], 2),
@@ -143,16 +1117,16 @@ mod tests {
         let rows = vec![vec![5, 1, 9, 5], vec![7, 5, 3], vec![2, 4, 6, 8]];
 
         // when
-        let result = checksum(&rows);
+        let result = checksum(&rows, 1);
 
         // then
-        assert_eq!(result, 18);
+        assert_eq!(result, Ok(18));
     }
 
     #[test]
     fn even_divide_row_should_calculate_correctly() {
         // given
-        let test_data: &[(Vec<i32>, i32)] = &[
+        let test_data: &[(Vec<i32>, i64)] = &[
             (vec![5, 9, 2, 8], 4),
             (vec![9, 4, 7, 3], 3),
             (vec![3, 8, 6, 5], 2),
@@ -160,7 +1134,7 @@ mod tests {
 
         // when
         for &(ref input, output) in test_data {
-            assert_eq!(even_divide_row(input), output);
+            assert_eq!(even_divide_row(input), Ok(output));
         }
     }
 
@@ -170,9 +1144,367 @@ mod tests {
         let input = &[vec![5, 9, 2, 8], vec![9, 4, 7, 3], vec![3, 8, 6, 5]];
 
         // when
-        let result = division_checksum(input);
+        let result = division_checksum(input, 1);
+
+        // then
+        assert_eq!(result, Ok(9));
+    }
+
+    #[test]
+    fn divisible_pairs_should_list_every_pair() {
+        // given
+        let row = &[2, 8, 3, 4, 5];
+
+        // when
+        let pairs = divisible_pairs(row);
+
+        // then
+        assert_eq!(
+            pairs,
+            &[
+                DivisiblePair {
+                    dividend: 4,
+                    divisor: 2,
+                    quotient: 2,
+                    count: 1,
+                },
+                DivisiblePair {
+                    dividend: 8,
+                    divisor: 2,
+                    quotient: 4,
+                    count: 1,
+                },
+                DivisiblePair {
+                    dividend: 8,
+                    divisor: 4,
+                    quotient: 2,
+                    count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn divisible_pairs_should_handle_duplicates_negatives_and_zeros() {
+        // given
+        let row = &[3, -3, 3, 0, -9];
+
+        // when
+        let pairs = divisible_pairs(row);
+
+        // then
+        let mut expected = vec![
+            DivisiblePair {
+                dividend: 0,
+                divisor: -3,
+                quotient: 0,
+                count: 1,
+            },
+            DivisiblePair {
+                dividend: 0,
+                divisor: 3,
+                quotient: 0,
+                count: 2,
+            },
+            DivisiblePair {
+                dividend: 0,
+                divisor: -9,
+                quotient: 0,
+                count: 1,
+            },
+            DivisiblePair {
+                dividend: 3,
+                divisor: 3,
+                quotient: 1,
+                count: 1,
+            },
+            DivisiblePair {
+                dividend: 3,
+                divisor: -3,
+                quotient: -1,
+                count: 2,
+            },
+            DivisiblePair {
+                dividend: -9,
+                divisor: -3,
+                quotient: 3,
+                count: 1,
+            },
+            DivisiblePair {
+                dividend: -9,
+                divisor: 3,
+                quotient: -3,
+                count: 2,
+            },
+        ];
+        expected.sort();
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn divisible_pairs_should_match_quadratic_scan() {
+        // given
+        let row: Vec<i32> = (0..300)
+            .map(|i| ((i * 7919) % 97 + 1) * (i % 3 + 1))
+            .collect();
+
+        // when
+        let pairs = divisible_pairs(&row);
+
+        // then
+        let mut expected_count = 0;
+        for i in 0..row.len() {
+            for j in i + 1..row.len() {
+                if row[i] % row[j] == 0 || row[j] % row[i] == 0 {
+                    expected_count += 1;
+                }
+            }
+        }
+        let count: usize = pairs.iter().map(|pair| pair.count).sum();
+        assert_eq!(count, expected_count);
+        for pair in &pairs {
+            assert_eq!(pair.dividend as i64, pair.divisor as i64 * pair.quotient);
+        }
+    }
+
+    #[test]
+    fn pairs_report_should_skip_blank_lines() {
+        // given
+        let rows = parse_lines("5 9 2 8\n\n5 7 11\n");
+
+        // when
+        let report = pairs_report(&rows, 1);
+
+        // then
+        assert_eq!(
+            report,
+            vec![
+                "row 1: 8 / 2 = 4".to_owned(),
+                "row 3: no evenly divisible pair".to_owned()
+            ]
+        );
+    }
+
+    #[test]
+    fn classify_row_should_distinguish_none_unique_and_several() {
+        // given
+        let test_data: &[(Vec<i32>, usize)] = &[
+            (vec![], 0),
+            (vec![5, 7, 11], 0),
+            (vec![5, 9, 2, 8], 1),
+            (vec![2, 4, 8], 3),
+            (vec![3, 3], 1),
+            (vec![3, 3, 3], 3),
+        ];
+
+        // when/then
+        for &(ref input, count) in test_data {
+            match classify_row(input) {
+                RowDivisibility::NoPair => assert_eq!(count, 0),
+                RowDivisibility::Unique(pair) => {
+                    assert_eq!(count, 1);
+                    assert_eq!(Ok(pair.quotient), even_divide_row(input));
+                }
+                RowDivisibility::Several(pairs) => {
+                    assert_eq!(pairs.iter().map(|pair| pair.count).sum::<usize>(), count)
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn builtin_metrics_should_match_existing_checksums() {
+        // given
+        let rows = vec![vec![5, 9, 2, 8], vec![9, 4, 7, 3], vec![3, 8, 6, 5]];
+
+        // when
+        let minmax = metric_checksum(&MinMaxSpread, &rows, 1);
+        let division = metric_checksum(&EvenDivision, &rows, 1);
+
+        // then
+        assert_eq!(minmax, checksum(&rows, 1).map(MetricValue::Integer));
+        assert_eq!(
+            division,
+            division_checksum(&rows, 1).map(MetricValue::Integer)
+        );
+    }
+
+    #[test]
+    fn median_spread_should_calculate_median_absolute_deviation() {
+        // given
+        let test_data: &[(Vec<i32>, f64)] = &[
+            (vec![], 0.0),
+            (vec![4], 0.0),
+            (vec![1, 1, 2, 2, 4, 6, 9], 1.0),
+            (vec![1, 2, 3, 4], 1.0),
+        ];
+
+        // when/then
+        for &(ref input, output) in test_data {
+            assert_eq!(MedianSpread.row_value(input), Ok(MetricValue::Real(output)));
+        }
+    }
+
+    #[test]
+    fn standard_deviation_should_calculate_population_deviation() {
+        // given
+        let row = &[2, 4, 4, 4, 5, 5, 7, 9];
+
+        // when
+        let result = StandardDeviation.row_value(row);
+
+        // then
+        assert_eq!(result, Ok(MetricValue::Real(2.0)));
+    }
+
+    #[test]
+    fn is_prime_should_match_trial_division() {
+        // given
+        let trial_division =
+            |n: i32| n >= 2 && (2..).take_while(|d| d * d <= n).all(|d| n % d != 0);
+
+        // when/then
+        for n in -5..20_000 {
+            assert_eq!(is_prime(n), trial_division(n), "n = {}", n);
+        }
+        assert!(is_prime(2_147_483_647));
+        assert!(!is_prime(2_147_483_641));
+    }
+
+    #[test]
+    fn prime_count_should_count_primes() {
+        // given
+        let row = &[-7, 0, 1, 2, 3, 4, 5, 97, 91];
+
+        // when
+        let result = PrimeCount.row_value(row);
+
+        // then
+        assert_eq!(result, Ok(MetricValue::Integer(4)));
+    }
+
+    #[test]
+    fn gcd_sum_should_add_gcds_of_all_pairs() {
+        // given
+        let row = &[12, 18, -8];
+
+        // when
+        let result = GcdSum.row_value(row);
+
+        // then
+        assert_eq!(result, Ok(MetricValue::Integer(6 + 4 + 2)));
+    }
+
+    #[test]
+    fn parse_metrics_should_select_metrics_by_name() {
+        // given
+        let names = "minmax, gcd,stddev".to_owned();
+
+        // when
+        let metrics = parse_metrics(Some(&names)).unwrap();
+
+        // then
+        let names: Vec<&str> = metrics.iter().map(|metric| metric.name()).collect();
+        assert_eq!(names, &["minmax", "gcd", "stddev"]);
+        assert!(parse_metrics(Some(&"minmax,mode".to_owned())).is_err());
+    }
+
+    #[test]
+    fn checksum_should_not_overflow_for_extreme_cells() {
+        // given
+        let rows = vec![vec![i32::MIN, i32::MAX]; 3];
+
+        // when
+        let result = checksum(&rows, 1);
+
+        // then
+        assert_eq!(result, Ok(3 * (u32::MAX as i64)));
+    }
+
+    #[test]
+    fn even_divide_row_should_not_overflow_for_min_divided_by_minus_one() {
+        // given
+        let row = &[i32::MIN, -1];
+
+        // when
+        let result = even_divide_row(row);
+
+        // then
+        assert_eq!(result, Ok(1 << 31));
+    }
+
+    #[test]
+    fn even_divide_row_should_divide_zero_but_not_by_zero() {
+        // given
+        let test_data: &[(Vec<i32>, Result<i64, RowError>)] = &[
+            (vec![7, 0, 3], Ok(0)),
+            (vec![0, 7], Ok(0)),
+            (vec![0], Ok(0)),
+            (vec![5, 0, 9, 0], Err(RowError::ZeroDivisor(4))),
+        ];
+
+        // when/then
+        for &(ref input, output) in test_data {
+            assert_eq!(even_divide_row(input), output);
+        }
+    }
+
+    #[test]
+    fn division_checksum_should_name_input_line_of_failing_row() {
+        // given
+        let input = "a b c d\n5 9 2 8\n0 4 0 3";
+        let options = TableOptions {
+            has_header: true,
+            ..TableOptions::default()
+        };
+
+        // when
+        let table = parse_table(input, &options).unwrap();
+        let result = division_checksum(&table.rows, table.first_line);
+
+        // then
+        assert_eq!(
+            result,
+            Err(ChecksumError {
+                row: 3,
+                error: RowError::ZeroDivisor(3),
+            })
+        );
+    }
+
+    #[test]
+    fn sum_rows_should_report_overflow() {
+        // given
+        let rows = vec![vec![1], vec![2], vec![3]];
+
+        // when
+        let result = sum_rows(&rows, 1, |_| Ok(i64::MAX / 2 + 1));
+
+        // then
+        assert_eq!(
+            result,
+            Err(ChecksumError {
+                row: 2,
+                error: RowError::Overflow,
+            })
+        );
+    }
+
+    #[test]
+    fn metric_checksum_should_report_failing_row() {
+        // given
+        let rows = vec![vec![2, 4], vec![0, 7, 0]];
+
+        // when
+        let result = metric_checksum(&EvenDivision, &rows, 5);
 
         // then
-        assert_eq!(result, 9);
+        assert_eq!(
+            result,
+            Err(ChecksumError {
+                row: 6,
+                error: RowError::ZeroDivisor(3),
+            })
+        );
     }
 }
@@ -1,18 +1,586 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
 
 fn main() {
-    let input: usize = 289326;
-    let (pos_x, pos_y) = pos_of_cell(input);
-    println!("Distance for cell {} is: {}", input, distance(pos_x, pos_y));
-
-    let first_value_larger = first_value_larger_than(input as u64);
-    println!(
-        "First value larger than the input is: {}",
-        first_value_larger
-    );
+    let args: Vec<String> = env::args().collect();
+    if let Err(err) = run(&args) {
+        println!("{}", err);
+    }
+}
+
+fn run(args: &[String]) -> Result<(), String> {
+    match args.get(1).map(|s| s.as_str()) {
+        Some("--pos") => {
+            let coordinates: Vec<Option<i64>> = args[2..].iter().map(|s| s.parse().ok()).collect();
+            match coordinates.as_slice() {
+                [Some(x), Some(y)] => match cell_of_pos(*x, *y) {
+                    Some(n) => println!("Cell at ({}, {}) is: {}", x, y, n),
+                    None => println!("The cell at ({}, {}) does not fit into 64 bits", x, y),
+                },
+                _ => return Err("Expected two coordinates after --pos".to_owned()),
+            }
+        }
+        Some("--fill") => {
+            let (fill, count) = parse_fill_args(&args[2..])?;
+            for (n, value) in (1..).zip(fill.take(count)) {
+                println!("{}: {}", n, value);
+            }
+        }
+        Some("--walk") => {
+            let (spiral, count) = parse_walk_args(&args[2..])?;
+            for (n, x, y) in spiral.take(count) {
+                println!("{}: ({}, {})", n, x, y);
+            }
+        }
+        Some("--render") => {
+            let (format, options, output) = parse_render_args(&args[2..])?;
+            let rendered = match format {
+                RenderFormat::Ascii => render_ascii(&options),
+                RenderFormat::Svg => render_svg(&options),
+            };
+            match output {
+                Some(path) => fs::write(path, rendered)
+                    .map_err(|e| format!("Unable to write {}: {}", path, e))?,
+                None => print!("{}", rendered),
+            }
+        }
+        Some(_) => {
+            let (cells, format) = parse_query_args(&args[1..])?;
+            let answers = answer_queries(&cells);
+            print!(
+                "{}",
+                match format {
+                    QueryFormat::Table => format_answers_table(&answers),
+                    QueryFormat::Json => format_answers_json(&answers),
+                }
+            );
+        }
+        None => return Err("No file name given".to_owned()),
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum QueryFormat {
+    Table,
+    Json,
+}
+
+// The cells are read from a file, from stdin if the file name is "-", or from the arguments
+// following --cells.
+fn parse_query_args(args: &[String]) -> Result<(Vec<u64>, QueryFormat), String> {
+    let mut cells: Vec<u64> = Vec::new();
+    let mut format = QueryFormat::Table;
+    let mut args = args.iter().peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().map(|s| s.as_str()) {
+                    Some("table") => QueryFormat::Table,
+                    Some("json") => QueryFormat::Json,
+                    _ => return Err("Expected table or json after --format".to_owned()),
+                }
+            }
+            "--cells" => {
+                while let Some(cell) = args.peek().filter(|s| !s.starts_with("--")) {
+                    cells.extend(parse_cells(cell)?);
+                    args.next();
+                }
+            }
+            "-" => {
+                let mut input = String::new();
+                io::stdin()
+                    .read_to_string(&mut input)
+                    .map_err(|e| format!("Unable to read stdin: {}", e))?;
+                cells.extend(parse_cells(&input)?);
+            }
+            option if option.starts_with("--") => {
+                return Err(format!("Unknown option '{}'", option))
+            }
+            filename => {
+                let input = fs::read_to_string(filename)
+                    .map_err(|e| format!("Unable to read input: {}", e))?;
+                cells.extend(parse_cells(&input)?);
+            }
+        }
+    }
+    Ok((cells, format))
+}
+
+fn parse_cells(input: &str) -> Result<Vec<u64>, String> {
+    input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .map(|s| match s.parse::<u64>() {
+            Ok(0) => Err(format!("Invalid cell number '{}': cells start at 1", s)),
+            Ok(cell) => Ok(cell),
+            Err(e) => Err(format!("Invalid cell number '{}': {}", s, e)),
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
+struct Answer {
+    cell: u64,
+    distance: u64,
+    first_larger_value: BigUint,
+}
+
+fn answer_queries(cells: &[u64]) -> Vec<Answer> {
+    cells
+        .iter()
+        .zip(first_values_larger_than(cells))
+        .map(|(&cell, first_larger_value)| {
+            let (x, y) = pos_of_cell(cell);
+            Answer {
+                cell,
+                distance: distance(x, y),
+                first_larger_value,
+            }
+        })
+        .collect()
+}
+
+fn format_answers_table(answers: &[Answer]) -> String {
+    let rows: Vec<[String; 3]> = answers
+        .iter()
+        .map(|a| {
+            [
+                a.cell.to_string(),
+                a.distance.to_string(),
+                a.first_larger_value.to_string(),
+            ]
+        })
+        .collect();
+    let header = ["cell", "distance", "first larger value"];
+    let widths: Vec<usize> = (0..3)
+        .map(|i| {
+            rows.iter()
+                .map(|r| r[i].len())
+                .fold(header[i].len(), usize::max)
+        })
+        .collect();
+    let mut output = format!(
+        "{:>w0$} | {:>w1$} | {:>w2$}\n{}-+-{}-+-{}\n",
+        header[0],
+        header[1],
+        header[2],
+        "-".repeat(widths[0]),
+        "-".repeat(widths[1]),
+        "-".repeat(widths[2]),
+        w0 = widths[0],
+        w1 = widths[1],
+        w2 = widths[2]
+    );
+    for row in rows {
+        output.push_str(&format!(
+            "{:>w0$} | {:>w1$} | {:>w2$}\n",
+            row[0],
+            row[1],
+            row[2],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        ));
+    }
+    output
+}
+
+// JSON readers commonly parse numbers as doubles, which are exact only up to 2^53 - 1. Cells and
+// values go beyond that, so they are always written as strings. Distances stay below 2^34.
+fn format_answers_json(answers: &[Answer]) -> String {
+    let entries: Vec<String> = answers
+        .iter()
+        .map(|a| {
+            format!(
+                "  {{\"cell\": \"{}\", \"distance\": {}, \"first_larger_value\": \"{}\"}}",
+                a.cell, a.distance, a.first_larger_value
+            )
+        })
+        .collect();
+    if entries.is_empty() {
+        "[]\n".to_owned()
+    } else {
+        format!("[\n{}\n]\n", entries.join(",\n"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderFormat {
+    Ascii,
+    Svg,
+}
+
+fn parse_render_args(
+    args: &[String],
+) -> Result<(RenderFormat, RenderOptions, Option<&String>), String> {
+    let format = match args.first().map(|s| s.as_str()) {
+        Some("ascii") => RenderFormat::Ascii,
+        Some("svg") => RenderFormat::Svg,
+        _ => return Err("Expected ascii or svg after --render".to_owned()),
+    };
+    let count = args
+        .get(1)
+        .and_then(|s| s.parse::<u64>().ok())
+        .ok_or_else(|| "Expected the number of cells to render".to_owned())?;
+    let mut options = RenderOptions {
+        count,
+        label: CellLabel::Index,
+        highlight: None,
+    };
+    let mut output = None;
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--values" => options.label = CellLabel::Value,
+            "--highlight" => {
+                options.highlight = Some(
+                    rest.next()
+                        .and_then(|s| s.parse::<u64>().ok())
+                        .ok_or_else(|| "Expected a cell number after --highlight".to_owned())?,
+                )
+            }
+            "--output" => {
+                output = Some(
+                    rest.next()
+                        .ok_or_else(|| "Expected a file name after --output".to_owned())?,
+                )
+            }
+            option => return Err(format!("Unknown render option '{}'", option)),
+        }
+    }
+    // A cell outside the rendered ones would draw its path outside of the picture.
+    if let Some(n) = options.highlight {
+        if n < 1 || n > count {
+            return Err(format!(
+                "Cannot highlight cell {}, only cells 1 to {} are rendered",
+                n, count
+            ));
+        }
+    }
+    Ok((format, options, output))
+}
+
+fn parse_walk_args(args: &[String]) -> Result<(Spiral, usize), String> {
+    let count = args
+        .first()
+        .and_then(|s| s.parse::<usize>().ok())
+        .ok_or_else(|| "Expected the number of cells to walk after --walk".to_owned())?;
+    let mut rings = Rings::Square;
+    let mut winding = Winding::CounterClockwise;
+    let mut start = Direction::East;
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--hex" => rings = Rings::Hexagonal,
+            "--clockwise" => winding = Winding::Clockwise,
+            "--start" => {
+                start = match rest.next().map(|s| s.as_str()) {
+                    Some("e") => Direction::East,
+                    Some("ne") => Direction::NorthEast,
+                    Some("n") => Direction::North,
+                    Some("nw") => Direction::NorthWest,
+                    Some("w") => Direction::West,
+                    Some("sw") => Direction::SouthWest,
+                    Some("s") => Direction::South,
+                    Some("se") => Direction::SouthEast,
+                    _ => {
+                        return Err(
+                            "Expected a compass direction like n or se after --start".to_owned()
+                        )
+                    }
+                }
+            }
+            option => return Err(format!("Unknown walk option '{}'", option)),
+        }
+    }
+    Ok((Spiral::new(rings, start, winding)?, count))
+}
+
+// The optional fourth argument lists the values of the first cells, separated by commas.
+fn parse_fill_args(args: &[String]) -> Result<(SpiralFill, usize), String> {
+    if args.len() != 3 && args.len() != 4 {
+        return Err(
+            "Expected neighbourhood, aggregate, number of cells and optional seeds after --fill"
+                .to_owned(),
+        );
+    }
+    let neighbourhood = match args[0].split_once(':') {
+        None if args[0] == "vonneumann" => Neighbourhood::VonNeumann,
+        None if args[0] == "moore" => Neighbourhood::Moore,
+        Some(("radius", r)) => match r.parse::<u32>() {
+            Ok(radius) if radius <= MAX_RADIUS => Neighbourhood::Radius(radius),
+            _ => {
+                return Err(format!(
+                    "Invalid radius '{}', expected 0 to {}",
+                    r, MAX_RADIUS
+                ))
+            }
+        },
+        _ => {
+            return Err(format!(
+                "Unknown neighbourhood '{}', expected vonneumann, moore or radius:R",
+                args[0]
+            ))
+        }
+    };
+    let aggregate = match args[1].split_once(':') {
+        None if args[1] == "sum" => Aggregate::Sum,
+        None if args[1] == "max" => Aggregate::Max,
+        None if args[1] == "xor" => Aggregate::Xor,
+        Some(("product", m)) => match m.parse::<u64>() {
+            Ok(m) if m > 0 => Aggregate::ProductMod(m),
+            _ => return Err(format!("Invalid modulus '{}'", m)),
+        },
+        _ => {
+            return Err(format!(
+                "Unknown aggregate '{}', expected sum, product:M, max or xor",
+                args[1]
+            ))
+        }
+    };
+    let count = args[2]
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid number of cells '{}': {}", args[2], e))?;
+    let fill = match args.get(3) {
+        None => SpiralFill::new(neighbourhood, aggregate),
+        Some(seeds) => SpiralFill::with_seeds(
+            neighbourhood,
+            aggregate,
+            parse_cells(seeds)?
+                .into_iter()
+                .map(BigUint::from_u64)
+                .collect(),
+        ),
+    };
+    Ok((fill, count))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    East,
+    NorthEast,
+    North,
+    NorthWest,
+    West,
+    SouthWest,
+    South,
+    SouthEast,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rings {
+    Square,
+    Hexagonal,
+}
+
+// Square spirals use the puzzle's coordinates, where north is -y. Hexagonal spirals use axial
+// coordinates (q, r) in the same orientation, so north-east is (1, -1) and north-west (0, -1).
+// Both are listed counter-clockwise.
+const SQUARE_DIRECTIONS: [(Direction, (i64, i64)); 4] = [
+    (Direction::East, (1, 0)),
+    (Direction::North, (0, -1)),
+    (Direction::West, (-1, 0)),
+    (Direction::South, (0, 1)),
+];
+const HEX_DIRECTIONS: [(Direction, (i64, i64)); 6] = [
+    (Direction::East, (1, 0)),
+    (Direction::NorthEast, (1, -1)),
+    (Direction::NorthWest, (0, -1)),
+    (Direction::West, (-1, 0)),
+    (Direction::SouthWest, (-1, 1)),
+    (Direction::SouthEast, (0, 1)),
+];
+
+// Yields (cell number, x, y), starting with cell 1 at the origin.
+//
+// Every ring k starts with one step outwards in the start direction d0, followed by the
+// segments d1 * (L - 1), d2 * L, ..., d0 * L, where the d are the directions in turning order
+// and L is the side length (2k for squares, k for hexagons). A ring ends at k * corner.
+#[derive(Debug, Clone)]
+struct Spiral {
+    rings: Rings,
+    directions: Vec<(i64, i64)>,
+    corner: (i64, i64),
+    next_cell: Option<u64>,
+    cursor: Cursor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cursor {
+    pos: (i64, i64),
+    ring: u64,
+    segment: usize,
+    taken: u64,
+}
+
+impl Default for Spiral {
+    fn default() -> Spiral {
+        Spiral::new(Rings::Square, Direction::East, Winding::CounterClockwise)
+            .expect("expected east to be a valid square direction")
+    }
+}
+
+impl Spiral {
+    fn new(rings: Rings, start: Direction, winding: Winding) -> Result<Spiral, String> {
+        let all: &[(Direction, (i64, i64))] = match rings {
+            Rings::Square => &SQUARE_DIRECTIONS,
+            Rings::Hexagonal => &HEX_DIRECTIONS,
+        };
+        let start_index = all
+            .iter()
+            .position(|&(d, _)| d == start)
+            .ok_or_else(|| format!("{:?} is not a direction of {:?} rings", start, rings))?;
+        let directions: Vec<(i64, i64)> = (0..all.len())
+            .map(|i| match winding {
+                Winding::CounterClockwise => all[(start_index + i) % all.len()].1,
+                Winding::Clockwise => all[(start_index + all.len() - i) % all.len()].1,
+            })
+            .collect();
+        let last = directions[directions.len() - 1];
+        let corner = match rings {
+            Rings::Square => (directions[0].0 + last.0, directions[0].1 + last.1),
+            Rings::Hexagonal => last,
+        };
+        Ok(Spiral {
+            rings,
+            directions,
+            corner,
+            next_cell: Some(1),
+            cursor: Cursor {
+                pos: (0, 0),
+                ring: 0,
+                segment: 0,
+                taken: 0,
+            },
+        })
+    }
+
+    fn side_len(&self, ring: u64) -> u64 {
+        match self.rings {
+            Rings::Square => 2 * ring,
+            Rings::Hexagonal => ring,
+        }
+    }
+
+    // The segments of a ring are numbered from 0 (the first step outwards) to the number of
+    // directions (the last side, which runs in the start direction again).
+    fn segment(&self, ring: u64, segment: usize) -> ((i64, i64), u64) {
+        let len = self.side_len(ring);
+        match segment {
+            0 => (self.directions[0], 1.min(len)),
+            1 => (self.directions[1], len.saturating_sub(1)),
+            s if s < self.directions.len() => (self.directions[s], len),
+            _ => (self.directions[0], len),
+        }
+    }
+
+    fn ring_and_offset(&self, index: u64) -> (u64, u64) {
+        let index = index as u128;
+        match self.rings {
+            Rings::Square => {
+                let ring = index.isqrt().div_ceil(2);
+                let before = (2 * ring - 1) * (2 * ring - 1);
+                (ring as u64, (index - before) as u64)
+            }
+            Rings::Hexagonal => {
+                let mut ring = (index / 3).isqrt();
+                while 3 * ring * (ring + 1) < index {
+                    ring += 1;
+                }
+                while ring > 1 && 3 * ring * (ring - 1) >= index {
+                    ring -= 1;
+                }
+                (ring as u64, (index - 3 * ring * (ring - 1) - 1) as u64)
+            }
+        }
+    }
+
+    fn locate(&self, cell: u64) -> Cursor {
+        if cell < 2 {
+            return Cursor {
+                pos: (0, 0),
+                ring: 0,
+                segment: 0,
+                taken: 0,
+            };
+        }
+        let (ring, offset) = self.ring_and_offset(cell - 1);
+        let k = ring as i64 - 1;
+        let mut cursor = Cursor {
+            pos: (k * self.corner.0, k * self.corner.1),
+            ring,
+            segment: 0,
+            taken: 0,
+        };
+        let mut steps = offset + 1;
+        for segment in 0..=self.directions.len() {
+            let ((dx, dy), len) = self.segment(ring, segment);
+            let taken = steps.min(len);
+            cursor.pos = (
+                cursor.pos.0 + dx * taken as i64,
+                cursor.pos.1 + dy * taken as i64,
+            );
+            cursor.segment = segment;
+            cursor.taken = taken;
+            steps -= taken;
+            if steps == 0 {
+                break;
+            }
+        }
+        cursor
+    }
+}
+
+impl Iterator for Spiral {
+    type Item = (u64, i64, i64);
+
+    fn next(&mut self) -> Option<(u64, i64, i64)> {
+        let cell = self.next_cell?;
+        self.next_cell = cell.checked_add(1);
+        if cell > 1 {
+            loop {
+                let (_, len) = self.segment(self.cursor.ring, self.cursor.segment);
+                if self.cursor.taken < len {
+                    break;
+                }
+                self.cursor.taken = 0;
+                self.cursor.segment += 1;
+                if self.cursor.segment > self.directions.len() {
+                    self.cursor.segment = 0;
+                    self.cursor.ring += 1;
+                }
+            }
+            let ((dx, dy), _) = self.segment(self.cursor.ring, self.cursor.segment);
+            self.cursor.pos = (self.cursor.pos.0 + dx, self.cursor.pos.1 + dy);
+            self.cursor.taken += 1;
+        }
+        Some((cell, self.cursor.pos.0, self.cursor.pos.1))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(u64, i64, i64)> {
+        let cell = self.next_cell?.checked_add(n as u64)?;
+        self.cursor = self.locate(cell);
+        self.next_cell = cell.checked_add(1);
+        Some((cell, self.cursor.pos.0, self.cursor.pos.1))
+    }
+}
+
+// The original step by step walker. It is only used by the tests as an independent reference
+// for the closed forms and the Spiral iterator.
+#[cfg(test)]
+#[derive(Debug, Clone)]
 struct State {
     pos_x: i64,
     pos_y: i64,
@@ -25,101 +593,487 @@ struct State {
     next: fn(State) -> State,
 }
 
+#[cfg(test)]
+impl PartialEq for State {
+    fn eq(&self, other: &State) -> bool {
+        self.pos_x == other.pos_x
+            && self.pos_y == other.pos_y
+            && self.right == other.right
+            && self.top == other.top
+            && self.left == other.left
+            && self.bottom == other.bottom
+            && std::ptr::fn_addr_eq(self.next, other.next)
+    }
+}
+
+#[cfg(test)]
+impl Eq for State {}
+
 fn distance(x: i64, y: i64) -> u64 {
-    (x.abs() as u64) + (y.abs() as u64)
+    x.unsigned_abs() + y.unsigned_abs()
 }
 
-fn first_value_larger_than(input: u64) -> u64 {
-    return if input == 0 {
-        1
-    } else {
-        let mut state = State {
-            pos_x: 1,
-            pos_y: 0,
-            right: 1,
-            top: 0,
-            left: 0,
-            bottom: 0,
-            next: move_up,
+// Answers all inputs with a single walk over the stress test values.
+fn first_values_larger_than(inputs: &[u64]) -> Vec<BigUint> {
+    let mut order: Vec<usize> = (0..inputs.len()).collect();
+    order.sort_by_key(|&i| inputs[i]);
+    let mut results: Vec<BigUint> = vec![BigUint::from_u64(0); inputs.len()];
+    let mut fill = SpiralFill::new(Neighbourhood::Moore, Aggregate::Sum);
+    let mut value = fill.next().expect("expected the spiral fill to be endless");
+    for i in order {
+        let threshold = BigUint::from_u64(inputs[i]);
+        while value < threshold {
+            value = fill.next().expect("expected the spiral fill to be endless");
+        }
+        results[i] = value.clone();
+    }
+    results
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Neighbourhood {
+    VonNeumann,
+    Moore,
+    // all cells within a Chebyshev distance of r, at most MAX_RADIUS
+    Radius(u32),
+}
+
+// Larger radii would allocate (2r + 1)^2 offsets and look up as many cells for every value.
+const MAX_RADIUS: u32 = 64;
+
+impl Neighbourhood {
+    fn offsets(self) -> Vec<(i64, i64)> {
+        let radius = match self {
+            Neighbourhood::VonNeumann => return vec![(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Neighbourhood::Moore => 1,
+            Neighbourhood::Radius(r) => r.min(MAX_RADIUS) as i64,
         };
-        let mut values: HashMap<(i64, i64), u64> = HashMap::with_capacity(1024);
-        values.insert((0, 0), 1);
-        values.insert((1, 0), 1);
-        let mut value: u64 = 1;
-        while value < input {
-            state = (state.next)(state);
-            value = value_of(state.pos_x, state.pos_y, &values);
-            values.insert((state.pos_x, state.pos_y), value);
+        let mut offsets = Vec::with_capacity(((2 * radius + 1) * (2 * radius + 1)) as usize);
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                if dx != 0 || dy != 0 {
+                    offsets.push((dx, dy));
+                }
+            }
         }
-        value
-    };
+        offsets
+    }
 }
 
-fn value_of(pos_x: i64, pos_y: i64, values: &HashMap<(i64, i64), u64>) -> u64 {
-    return *values.get(&(pos_x - 1, pos_y - 1)).unwrap_or(&0) +
-        *values.get(&(pos_x - 1, pos_y)).unwrap_or(&0) +
-        *values.get(&(pos_x - 1, pos_y + 1)).unwrap_or(&0) +
-        *values.get(&(pos_x, pos_y - 1)).unwrap_or(&0) +
-        *values.get(&(pos_x, pos_y + 1)).unwrap_or(&0) +
-        *values.get(&(pos_x + 1, pos_y - 1)).unwrap_or(&0) +
-        *values.get(&(pos_x + 1, pos_y)).unwrap_or(&0) +
-        *values.get(&(pos_x + 1, pos_y + 1)).unwrap_or(&0);
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Aggregate {
+    Sum,
+    ProductMod(u64),
+    Max,
+    Xor,
 }
 
-fn pos_of_cell(n: usize) -> (i64, i64) {
-    return if n < 2 {
-        (0, 0)
-    } else {
-        let mut state = State {
-            pos_x: 1,
-            pos_y: 0,
-            right: 1,
-            top: 0,
-            left: 0,
-            bottom: 0,
-            next: move_up,
+// Fills the spiral cell by cell: the first cells take the seed values, every further cell
+// aggregates the cells in its neighbourhood that were filled before it. Values are stored by cell
+// number, so the neighbours are looked up with `cell_of_pos`.
+struct SpiralFill {
+    offsets: Vec<(i64, i64)>,
+    aggregate: Aggregate,
+    seeds: Vec<BigUint>,
+    values: Vec<BigUint>,
+    spiral: Spiral,
+}
+
+impl SpiralFill {
+    // the puzzle's fill, which starts with a single 1
+    fn new(neighbourhood: Neighbourhood, aggregate: Aggregate) -> SpiralFill {
+        SpiralFill::with_seeds(neighbourhood, aggregate, vec![BigUint::from_u64(1)])
+    }
+
+    fn with_seeds(
+        neighbourhood: Neighbourhood,
+        aggregate: Aggregate,
+        seeds: Vec<BigUint>,
+    ) -> SpiralFill {
+        SpiralFill {
+            offsets: neighbourhood.offsets(),
+            aggregate,
+            seeds,
+            values: Vec::with_capacity(1024),
+            spiral: Spiral::default(),
+        }
+    }
+
+    fn value_at(&self, x: i64, y: i64) -> Option<&BigUint> {
+        cell_of_pos(x, y)
+            .and_then(|n| usize::try_from(n - 1).ok())
+            .and_then(|i| self.values.get(i))
+    }
+
+    fn aggregate_neighbours(&self, x: i64, y: i64) -> BigUint {
+        let neighbours = self
+            .offsets
+            .iter()
+            .filter_map(|&(dx, dy)| self.value_at(x + dx, y + dy));
+        match self.aggregate {
+            Aggregate::Sum => neighbours.fold(BigUint::from_u64(0), |sum, v| sum.add(v)),
+            Aggregate::ProductMod(modulus) => {
+                let product = neighbours.fold(1 % modulus as u128, |product, v| {
+                    product * v.rem_u64(modulus) as u128 % modulus as u128
+                });
+                BigUint::from_u64(product as u64)
+            }
+            Aggregate::Max => neighbours
+                .max()
+                .cloned()
+                .unwrap_or_else(|| BigUint::from_u64(0)),
+            Aggregate::Xor => neighbours.fold(BigUint::from_u64(0), |acc, v| acc.xor(v)),
+        }
+    }
+}
+
+impl Iterator for SpiralFill {
+    type Item = BigUint;
+
+    fn next(&mut self) -> Option<BigUint> {
+        let (_, x, y) = self.spiral.next()?;
+        let value = match self.seeds.get(self.values.len()) {
+            Some(seed) => seed.clone(),
+            None => self.aggregate_neighbours(x, y),
         };
-        for _ in 2..n {
-            state = (state.next)(state);
+        self.values.push(value.clone());
+        Some(value)
+    }
+}
+
+// Just enough of an arbitrary precision unsigned integer for the spiral fill.
+// The limbs are little endian and never have trailing zeros.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct BigUint {
+    limbs: Vec<u32>,
+}
+
+impl BigUint {
+    fn from_u64(value: u64) -> BigUint {
+        let mut result = BigUint {
+            limbs: vec![value as u32, (value >> 32) as u32],
+        };
+        result.normalize();
+        result
+    }
+
+    fn normalize(&mut self) {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
         }
-        (state.pos_x, state.pos_y)
+    }
+
+    fn add(mut self, other: &BigUint) -> BigUint {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+        let mut carry = 0u64;
+        for (i, limb) in self.limbs.iter_mut().enumerate() {
+            let sum = *limb as u64 + *other.limbs.get(i).unwrap_or(&0) as u64 + carry;
+            *limb = sum as u32;
+            carry = sum >> 32;
+        }
+        if carry > 0 {
+            self.limbs.push(carry as u32);
+        }
+        self
+    }
+
+    fn xor(mut self, other: &BigUint) -> BigUint {
+        if self.limbs.len() < other.limbs.len() {
+            self.limbs.resize(other.limbs.len(), 0);
+        }
+        for (limb, other) in self.limbs.iter_mut().zip(other.limbs.iter()) {
+            *limb ^= other;
+        }
+        self.normalize();
+        self
+    }
+
+    fn rem_u64(&self, modulus: u64) -> u64 {
+        self.limbs.iter().rev().fold(0u128, |rem, &limb| {
+            ((rem << 32) | limb as u128) % modulus as u128
+        }) as u64
+    }
+
+    fn div_rem_u32(&self, divisor: u32) -> (BigUint, u32) {
+        let mut quotient = BigUint {
+            limbs: vec![0; self.limbs.len()],
+        };
+        let mut rem = 0u64;
+        for (i, &limb) in self.limbs.iter().enumerate().rev() {
+            let current = (rem << 32) | limb as u64;
+            quotient.limbs[i] = (current / divisor as u64) as u32;
+            rem = current % divisor as u64;
+        }
+        quotient.normalize();
+        (quotient, rem as u32)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.limbs
+            .len()
+            .cmp(&other.limbs.len())
+            .then_with(|| self.limbs.iter().rev().cmp(other.limbs.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut chunks: Vec<u32> = Vec::new();
+        let mut rest = self.clone();
+        while !rest.limbs.is_empty() {
+            let (quotient, rem) = rest.div_rem_u32(1_000_000_000);
+            chunks.push(rem);
+            rest = quotient;
+        }
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((first, rest)) => {
+                write!(f, "{}", first)?;
+                for chunk in rest.iter().rev() {
+                    write!(f, "{:09}", chunk)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellLabel {
+    Index,
+    // the stress test value, the Moore neighbourhood sums
+    Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RenderOptions {
+    count: u64,
+    label: CellLabel,
+    // the Manhattan path from this cell to the origin is highlighted, the cell must be one of
+    // the rendered cells
+    highlight: Option<u64>,
+}
+
+struct LabelledCell {
+    x: i64,
+    y: i64,
+    label: String,
+    highlighted: bool,
+}
+
+fn labelled_cells(options: &RenderOptions) -> Vec<LabelledCell> {
+    let path: HashSet<(i64, i64)> = options
+        .highlight
+        .map(|n| {
+            let (x, y) = pos_of_cell(n);
+            manhattan_path(x, y).into_iter().collect()
+        })
+        .unwrap_or_default();
+    let labels: Box<dyn Iterator<Item = String>> = match options.label {
+        CellLabel::Index => Box::new((1..).map(|n: u64| n.to_string())),
+        CellLabel::Value => {
+            Box::new(SpiralFill::new(Neighbourhood::Moore, Aggregate::Sum).map(|v| v.to_string()))
+        }
+    };
+    Spiral::default()
+        .take(options.count as usize)
+        .zip(labels)
+        .map(|((_, x, y), label)| LabelledCell {
+            x,
+            y,
+            label,
+            highlighted: path.contains(&(x, y)),
+        })
+        .collect()
+}
+
+// Walks horizontally first, then vertically. The path contains both ends.
+fn manhattan_path(x: i64, y: i64) -> Vec<(i64, i64)> {
+    let mut path = Vec::with_capacity(distance(x, y) as usize + 1);
+    let (mut px, mut py) = (x, y);
+    path.push((px, py));
+    while px != 0 {
+        px -= px.signum();
+        path.push((px, py));
+    }
+    while py != 0 {
+        py -= py.signum();
+        path.push((px, py));
+    }
+    path
+}
+
+fn bounds(cells: &[LabelledCell]) -> (i64, i64, i64, i64) {
+    cells
+        .iter()
+        .fold((0, 0, 0, 0), |(min_x, min_y, max_x, max_y), c| {
+            (
+                min_x.min(c.x),
+                min_y.min(c.y),
+                max_x.max(c.x),
+                max_y.max(c.y),
+            )
+        })
+}
+
+// Highlighted cells are put into brackets.
+fn render_ascii(options: &RenderOptions) -> String {
+    let cells = labelled_cells(options);
+    let (min_x, min_y, max_x, max_y) = bounds(&cells);
+    let width = cells.iter().map(|c| c.label.len()).max().unwrap_or(0) + 2;
+    let columns = (max_x - min_x + 1) as usize;
+    let mut grid: Vec<Vec<String>> =
+        vec![vec![" ".repeat(width); columns]; (max_y - min_y + 1) as usize];
+    for cell in &cells {
+        let text = if cell.highlighted {
+            format!("[{}]", cell.label)
+        } else {
+            cell.label.clone()
+        };
+        grid[(cell.y - min_y) as usize][(cell.x - min_x) as usize] =
+            format!("{:>w$}", text, w = width);
+    }
+    let mut output = String::new();
+    for row in grid {
+        output.push_str(row.join(" ").trim_end());
+        output.push('\n');
+    }
+    output
+}
+
+const SVG_CELL_SIZE: i64 = 40;
+
+fn render_svg(options: &RenderOptions) -> String {
+    let cells = labelled_cells(options);
+    let (min_x, min_y, max_x, max_y) = bounds(&cells);
+    let label_len = cells.iter().map(|c| c.label.len()).max().unwrap_or(1);
+    let cell_width = SVG_CELL_SIZE.max(label_len as i64 * 9 + 8);
+    let to_svg = |x: i64, y: i64| ((x - min_x) * cell_width, (y - min_y) * SVG_CELL_SIZE);
+
+    let mut output = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"monospace\" font-size=\"14\">\n",
+        (max_x - min_x + 1) * cell_width,
+        (max_y - min_y + 1) * SVG_CELL_SIZE
+    );
+    for cell in &cells {
+        let (left, top) = to_svg(cell.x, cell.y);
+        let fill = if cell.highlighted {
+            "#ffd27f"
+        } else {
+            "#ffffff"
+        };
+        output.push_str(&format!(
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"#888888\"/>\n",
+            left, top, cell_width, SVG_CELL_SIZE, fill
+        ));
+        output.push_str(&format!(
+            "  <text x=\"{}\" y=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+            left + cell_width / 2,
+            top + SVG_CELL_SIZE / 2,
+            cell.label
+        ));
+    }
+    if let Some(n) = options.highlight {
+        let (x, y) = pos_of_cell(n);
+        let points: Vec<String> = manhattan_path(x, y)
+            .into_iter()
+            .map(|(px, py)| {
+                let (left, top) = to_svg(px, py);
+                format!("{},{}", left + cell_width / 2, top + SVG_CELL_SIZE / 2)
+            })
+            .collect();
+        output.push_str(&format!(
+            "  <polyline points=\"{}\" fill=\"none\" stroke=\"#d03000\" stroke-width=\"3\" stroke-opacity=\"0.6\"/>\n",
+            points.join(" ")
+        ));
+    }
+    output.push_str("</svg>\n");
+    output
+}
+
+// The position of cell n on the puzzle's spiral. Cell 0 is treated like cell 1.
+fn pos_of_cell(n: u64) -> (i64, i64) {
+    let (_, x, y) = Spiral::default()
+        .nth(n.saturating_sub(1) as usize)
+        .expect("expected every u64 cell to be on the spiral");
+    (x, y)
+}
+
+// Inverse of `pos_of_cell`, using the same rings as `Spiral`: ring k holds the cells
+// ((2k-1)^2, (2k+1)^2] and has four sides of length 2k, up along x = k, left along y = -k,
+// down along x = -k and right along y = k. None if the cell number does not fit into a u64.
+fn cell_of_pos(x: i64, y: i64) -> Option<u64> {
+    let k = x.unsigned_abs().max(y.unsigned_abs()) as u128;
+    if k == 0 {
+        return Some(1);
+    }
+    if k > 1 << 31 {
+        return None;
+    }
+    let (x, y) = (x as i128, y as i128);
+    let ki = k as i128;
+    let (side, p) = if x == ki && y < ki {
+        (0, ki - 1 - y)
+    } else if y == -ki && x < ki {
+        (1, ki - 1 - x)
+    } else if x == -ki {
+        (2, y + ki - 1)
+    } else {
+        (3, x + ki - 1)
     };
+    let side_len = 2 * k;
+    let n = (side_len - 1) * (side_len - 1) + 1 + side * side_len + p as u128;
+    u64::try_from(n).ok()
 }
 
+#[cfg(test)]
 fn move_up(mut state: State) -> State {
-    state.pos_y = state.pos_y - 1;
+    state.pos_y -= 1;
     if state.top > state.pos_y {
         state.top = state.pos_y;
         state.next = move_left;
     }
-    return state;
+    state
 }
 
+#[cfg(test)]
 fn move_left(mut state: State) -> State {
-    state.pos_x = state.pos_x - 1;
+    state.pos_x -= 1;
     if state.left > state.pos_x {
-        state.left = state.pos_y;
+        state.left = state.pos_x;
         state.next = move_down;
     }
-    return state;
+    state
 }
 
+#[cfg(test)]
 fn move_down(mut state: State) -> State {
-    state.pos_y = state.pos_y + 1;
+    state.pos_y += 1;
     if state.bottom < state.pos_y {
         state.bottom = state.pos_y;
         state.next = move_right;
     }
-    return state;
+    state
 }
 
+#[cfg(test)]
 fn move_right(mut state: State) -> State {
-    state.pos_x = state.pos_x + 1;
+    state.pos_x += 1;
     if state.right < state.pos_x {
         state.right = state.pos_x;
         state.next = move_up;
     }
-    return state;
+    state
 }
 
 #[cfg(test)]
@@ -147,7 +1101,7 @@ mod tests {
     #[test]
     fn pos_of_cell_should_caclulate_cell_position() {
         // given
-        let test_data: &[(usize, (i64, i64))] = &[
+        let test_data: &[(u64, (i64, i64))] = &[
             (0, (0, 0)),
             (1, (0, 0)),
             (2, (1, 0)),
@@ -162,6 +1116,559 @@ mod tests {
         }
     }
 
+    #[test]
+    fn pos_of_cell_and_cell_of_pos_should_match_walker() {
+        // given
+        let mut state = State {
+            pos_x: 1,
+            pos_y: 0,
+            right: 1,
+            top: 0,
+            left: 0,
+            bottom: 0,
+            next: move_up,
+        };
+        let mut spiral = Spiral::default();
+        assert_eq!(pos_of_cell(1), (0, 0));
+        assert_eq!(cell_of_pos(0, 0), Some(1));
+        assert_eq!(spiral.next(), Some((1, 0, 0)));
+
+        // when/then
+        for n in 2..=1_000_000 {
+            assert_eq!(pos_of_cell(n), (state.pos_x, state.pos_y), "cell {}", n);
+            assert_eq!(cell_of_pos(state.pos_x, state.pos_y), Some(n));
+            assert_eq!(spiral.next(), Some((n, state.pos_x, state.pos_y)));
+            state = (state.next)(state);
+        }
+    }
+
+    #[test]
+    fn spiral_should_walk_all_windings_and_starts() {
+        // given
+        type Walk = [(i64, i64); 8];
+        let test_data: &[(Rings, Direction, Winding, Walk)] = &[
+            (
+                Rings::Square,
+                Direction::East,
+                Winding::CounterClockwise,
+                [
+                    (0, 0),
+                    (1, 0),
+                    (1, -1),
+                    (0, -1),
+                    (-1, -1),
+                    (-1, 0),
+                    (-1, 1),
+                    (0, 1),
+                ],
+            ),
+            (
+                Rings::Square,
+                Direction::East,
+                Winding::Clockwise,
+                [
+                    (0, 0),
+                    (1, 0),
+                    (1, 1),
+                    (0, 1),
+                    (-1, 1),
+                    (-1, 0),
+                    (-1, -1),
+                    (0, -1),
+                ],
+            ),
+            (
+                Rings::Square,
+                Direction::North,
+                Winding::CounterClockwise,
+                [
+                    (0, 0),
+                    (0, -1),
+                    (-1, -1),
+                    (-1, 0),
+                    (-1, 1),
+                    (0, 1),
+                    (1, 1),
+                    (1, 0),
+                ],
+            ),
+            (
+                Rings::Hexagonal,
+                Direction::East,
+                Winding::CounterClockwise,
+                [
+                    (0, 0),
+                    (1, 0),
+                    (1, -1),
+                    (0, -1),
+                    (-1, 0),
+                    (-1, 1),
+                    (0, 1),
+                    (1, 1),
+                ],
+            ),
+            (
+                Rings::Hexagonal,
+                Direction::West,
+                Winding::Clockwise,
+                [
+                    (0, 0),
+                    (-1, 0),
+                    (0, -1),
+                    (1, -1),
+                    (1, 0),
+                    (0, 1),
+                    (-1, 1),
+                    (-2, 1),
+                ],
+            ),
+        ];
+
+        // when/then
+        for &(rings, start, winding, ref expected) in test_data {
+            let spiral = Spiral::new(rings, start, winding).unwrap();
+            let positions: Vec<(i64, i64)> = spiral.take(8).map(|(_, x, y)| (x, y)).collect();
+            assert_eq!(positions, expected, "{:?} {:?} {:?}", rings, start, winding);
+        }
+    }
+
+    #[test]
+    fn spiral_should_reject_directions_of_other_rings() {
+        // when
+        let square = Spiral::new(Rings::Square, Direction::NorthEast, Winding::Clockwise);
+        let hex = Spiral::new(Rings::Hexagonal, Direction::North, Winding::Clockwise);
+
+        // then
+        assert!(square.is_err());
+        assert!(hex.is_err());
+    }
+
+    #[test]
+    fn spiral_hexagonal_rings_should_be_contiguous() {
+        // given
+        let hex_distance = |(q, r): (i64, i64)| (q.abs() + r.abs() + (q + r).abs()) / 2;
+        let spiral =
+            Spiral::new(Rings::Hexagonal, Direction::SouthWest, Winding::Clockwise).unwrap();
+
+        // when
+        let positions: Vec<(i64, i64)> = spiral
+            .take(1 + 3 * 20 * 21)
+            .map(|(_, x, y)| (x, y))
+            .collect();
+
+        // then
+        for pair in positions.windows(2) {
+            let step = (pair[1].0 - pair[0].0, pair[1].1 - pair[0].1);
+            assert_eq!(hex_distance(step), 1);
+        }
+        let mut seen = positions.clone();
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), positions.len());
+        assert_eq!(positions.iter().map(|&p| hex_distance(p)).max(), Some(20));
+    }
+
+    #[test]
+    fn spiral_nth_should_match_walk() {
+        // given
+        let spirals = &[
+            Spiral::default(),
+            Spiral::new(Rings::Square, Direction::South, Winding::Clockwise).unwrap(),
+            Spiral::new(
+                Rings::Hexagonal,
+                Direction::NorthWest,
+                Winding::CounterClockwise,
+            )
+            .unwrap(),
+        ];
+
+        // when/then
+        for spiral in spirals {
+            let walked: Vec<(u64, i64, i64)> = spiral.clone().take(2000).collect();
+            for n in [0, 1, 2, 7, 8, 9, 10, 24, 25, 26, 36, 37, 38, 999, 1999] {
+                assert_eq!(spiral.clone().nth(n), Some(walked[n]));
+            }
+            let mut skipping = spiral.clone();
+            assert_eq!(skipping.nth(100), Some(walked[100]));
+            assert_eq!(skipping.next(), Some(walked[101]));
+        }
+    }
+
+    #[test]
+    fn pos_of_cell_should_handle_largest_cells() {
+        // given
+        let test_data: &[u64] = &[
+            u64::MAX,
+            u64::MAX - 1,
+            (u32::MAX as u64) * (u32::MAX as u64),
+            (u32::MAX as u64) * (u32::MAX as u64) + 1,
+            1 << 63,
+        ];
+
+        // when/then
+        for &n in test_data {
+            let (x, y) = pos_of_cell(n);
+            assert_eq!(cell_of_pos(x, y), Some(n));
+        }
+        assert_eq!(pos_of_cell(u64::MAX), (-2147483646, -2147483648));
+    }
+
+    #[test]
+    fn cell_of_pos_should_reject_cells_beyond_u64() {
+        // given
+        let (x, y) = pos_of_cell(u64::MAX);
+
+        // when
+        let next = cell_of_pos(x - 1, y);
+        let far = cell_of_pos(i64::MIN, i64::MAX);
+
+        // then
+        assert_eq!(next, None);
+        assert_eq!(far, None);
+    }
+
+    #[test]
+    fn first_values_larger_than_should_find_stress_test_values() {
+        // given
+        let test_data: &[(u64, u64)] = &[
+            (0, 1),
+            (2, 2),
+            (3, 4),
+            (24, 25),
+            (748, 806),
+            (289326, 295229),
+        ];
+
+        // when/then
+        for &(input, output) in test_data {
+            assert_eq!(
+                first_values_larger_than(&[input]),
+                vec![BigUint::from_u64(output)]
+            );
+        }
+    }
+
+    #[test]
+    fn first_values_larger_than_should_answer_unsorted_batches() {
+        // given
+        let inputs = &[748, 0, 24, 289326, 24, 3];
+
+        // when
+        let result = first_values_larger_than(inputs);
+
+        // then
+        let expected: Vec<BigUint> = [806, 1, 25, 295229, 25, 4]
+            .iter()
+            .map(|&v| BigUint::from_u64(v))
+            .collect();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn parse_cells_should_accept_whitespace_and_commas() {
+        // given
+        let input = "1 12,\n23\t1024\n";
+
+        // when
+        let cells = parse_cells(input);
+
+        // then
+        assert_eq!(cells, Ok(vec![1, 12, 23, 1024]));
+        assert!(parse_cells("12 x3").is_err());
+        assert_eq!(
+            parse_cells("12 0"),
+            Err("Invalid cell number '0': cells start at 1".to_owned())
+        );
+    }
+
+    #[test]
+    fn parse_query_args_should_read_cells_and_format() {
+        // given
+        let args: Vec<String> = ["--cells", "12", "23,1024", "--format", "json"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        // when
+        let result = parse_query_args(&args);
+
+        // then
+        assert_eq!(result, Ok((vec![12, 23, 1024], QueryFormat::Json)));
+    }
+
+    #[test]
+    fn format_answers_should_render_table_and_json() {
+        // given
+        let answers = answer_queries(&[1, 12, 1024]);
+
+        // when
+        let table = format_answers_table(&answers);
+        let json = format_answers_json(&answers);
+
+        // then
+        assert_eq!(
+            table,
+            concat!(
+                "cell | distance | first larger value\n",
+                "-----+----------+-------------------\n",
+                "   1 |        0 |                  1\n",
+                "  12 |        3 |                 23\n",
+                "1024 |       31 |               1968\n",
+            )
+        );
+        assert_eq!(
+            json,
+            concat!(
+                "[\n",
+                "  {\"cell\": \"1\", \"distance\": 0, \"first_larger_value\": \"1\"},\n",
+                "  {\"cell\": \"12\", \"distance\": 3, \"first_larger_value\": \"23\"},\n",
+                "  {\"cell\": \"1024\", \"distance\": 31, \"first_larger_value\": \"1968\"}\n",
+                "]\n",
+            )
+        );
+    }
+
+    #[test]
+    fn format_answers_json_should_keep_field_types_for_large_integers() {
+        // given
+        let answers = answer_queries(&[9007199254740991, 9007199254740992, u64::MAX]);
+
+        // when
+        let json = format_answers_json(&answers);
+
+        // then
+        let lines: Vec<&str> = json.lines().collect();
+        assert!(lines[1].starts_with("  {\"cell\": \"9007199254740991\", \"distance\": 71321766, "));
+        assert!(lines[2].starts_with("  {\"cell\": \"9007199254740992\", "));
+        assert!(lines[3].starts_with("  {\"cell\": \"18446744073709551615\", \"distance\": "));
+        assert!(lines[3].ends_with(&format!(
+            "\"first_larger_value\": \"{}\"}}",
+            answers[2].first_larger_value
+        )));
+    }
+
+    #[test]
+    fn spiral_fill_should_produce_moore_sums() {
+        // given
+        let fill = SpiralFill::new(Neighbourhood::Moore, Aggregate::Sum);
+
+        // when
+        let values: Vec<String> = fill.take(12).map(|v| v.to_string()).collect();
+
+        // then
+        assert_eq!(
+            values,
+            &["1", "1", "2", "4", "5", "10", "11", "23", "25", "26", "54", "57"]
+        );
+    }
+
+    #[test]
+    fn spiral_fill_should_support_other_neighbourhoods_and_aggregates() {
+        // given
+        let test_data: &[(Neighbourhood, Aggregate, [u64; 10])] = &[
+            (
+                Neighbourhood::VonNeumann,
+                Aggregate::Sum,
+                [1, 1, 1, 2, 2, 3, 3, 4, 5, 5],
+            ),
+            (
+                Neighbourhood::Radius(2),
+                Aggregate::Sum,
+                [1, 1, 2, 4, 8, 16, 32, 64, 128, 200],
+            ),
+            (
+                Neighbourhood::Moore,
+                Aggregate::Xor,
+                [1, 1, 0, 0, 1, 0, 1, 1, 1, 0],
+            ),
+        ];
+
+        // when/then
+        for &(neighbourhood, aggregate, ref expected) in test_data {
+            let values: Vec<BigUint> = SpiralFill::new(neighbourhood, aggregate).take(10).collect();
+            let expected: Vec<BigUint> = expected.iter().map(|&v| BigUint::from_u64(v)).collect();
+            assert_eq!(values, expected, "{:?} {:?}", neighbourhood, aggregate);
+        }
+    }
+
+    #[test]
+    fn spiral_fill_aggregates_should_differ_for_seeded_cells() {
+        // given
+        let seeds = || vec![3, 1, 4].into_iter().map(BigUint::from_u64).collect();
+        let test_data: &[(Aggregate, [u64; 12])] = &[
+            (
+                Aggregate::Sum,
+                [3, 1, 4, 8, 11, 22, 25, 51, 55, 56, 116, 121],
+            ),
+            (
+                Aggregate::ProductMod(1000),
+                [3, 1, 4, 12, 36, 296, 888, 544, 632, 632, 696, 784],
+            ),
+            (Aggregate::Max, [3, 1, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4]),
+            (Aggregate::Xor, [3, 1, 4, 6, 5, 0, 3, 1, 3, 2, 4, 1]),
+        ];
+
+        // when/then
+        for &(aggregate, ref expected) in test_data {
+            let values: Vec<BigUint> =
+                SpiralFill::with_seeds(Neighbourhood::Moore, aggregate, seeds())
+                    .take(12)
+                    .collect();
+            let expected: Vec<BigUint> = expected.iter().map(|&v| BigUint::from_u64(v)).collect();
+            assert_eq!(values, expected, "{:?}", aggregate);
+        }
+    }
+
+    #[test]
+    fn parse_fill_args_should_reject_huge_radius() {
+        // given
+        let args = |radius: &str| -> Vec<String> {
+            vec![
+                format!("radius:{}", radius),
+                "sum".to_owned(),
+                "10".to_owned(),
+            ]
+        };
+
+        // when
+        let largest = parse_fill_args(&args("64"));
+        let huge = parse_fill_args(&args("4294967295"));
+
+        // then
+        assert!(largest.is_ok());
+        assert_eq!(
+            huge.err(),
+            Some("Invalid radius '4294967295', expected 0 to 64".to_owned())
+        );
+    }
+
+    #[test]
+    fn spiral_fill_should_not_overflow() {
+        // given
+        let fill = SpiralFill::new(Neighbourhood::Moore, Aggregate::Sum);
+
+        // when
+        let values: Vec<BigUint> = fill.take(5000).collect();
+
+        // then
+        assert!(values.windows(2).all(|w| w[0] <= w[1]));
+        assert!(values[4999].limbs.len() > 2);
+    }
+
+    #[test]
+    fn big_uint_should_add_and_print() {
+        // given
+        let a = BigUint::from_u64(u64::MAX);
+        let b = BigUint::from_u64(1);
+
+        // when
+        let sum = a.clone().add(&b).add(&a);
+
+        // then
+        assert_eq!(sum.to_string(), "36893488147419103231");
+        assert_eq!(
+            sum.rem_u64(1_000_000_007),
+            (36893488147419103231u128 % 1_000_000_007) as u64
+        );
+        assert_eq!(BigUint::from_u64(0).to_string(), "0");
+        assert_eq!(a.clone().xor(&a), BigUint::from_u64(0));
+        assert!(a < sum);
+    }
+
+    #[test]
+    fn manhattan_path_should_lead_to_origin() {
+        // given
+        let (x, y) = pos_of_cell(23);
+
+        // when
+        let path = manhattan_path(x, y);
+
+        // then
+        assert_eq!(path, &[(0, 2), (0, 1), (0, 0)]);
+        assert_eq!(manhattan_path(0, 0), &[(0, 0)]);
+        assert_eq!(manhattan_path(-2, 1), &[(-2, 1), (-1, 1), (0, 1), (0, 0)]);
+    }
+
+    #[test]
+    fn render_ascii_should_draw_aligned_grid() {
+        // given
+        let options = RenderOptions {
+            count: 12,
+            label: CellLabel::Index,
+            highlight: Some(12),
+        };
+
+        // when
+        let output = render_ascii(&options);
+
+        // then
+        assert_eq!(
+            output,
+            concat!(
+                "   5  [4]  [3] [12]\n",
+                "   6  [1]    2   11\n",
+                "   7    8    9   10\n",
+            )
+        );
+    }
+
+    #[test]
+    fn render_ascii_should_show_stress_test_values() {
+        // given
+        let options = RenderOptions {
+            count: 9,
+            label: CellLabel::Value,
+            highlight: None,
+        };
+
+        // when
+        let output = render_ascii(&options);
+
+        // then
+        assert_eq!(output, "   5    4    2\n  10    1    1\n  11   23   25\n");
+    }
+
+    #[test]
+    fn parse_render_args_should_reject_highlight_beyond_count() {
+        // given
+        let highlight = |cell: &str| {
+            let args: Vec<String> = ["svg", "9", "--highlight", cell]
+                .iter()
+                .map(|s| s.to_string())
+                .collect();
+            parse_render_args(&args).map(|(_, options, _)| options.highlight)
+        };
+
+        // when/then
+        assert_eq!(highlight("9"), Ok(Some(9)));
+        assert_eq!(
+            highlight("10"),
+            Err("Cannot highlight cell 10, only cells 1 to 9 are rendered".to_owned())
+        );
+        assert!(highlight("18446744073709551615").is_err());
+        assert!(highlight("0").is_err());
+    }
+
+    #[test]
+    fn render_svg_should_draw_cells_and_path() {
+        // given
+        let options = RenderOptions {
+            count: 9,
+            label: CellLabel::Index,
+            highlight: Some(9),
+        };
+
+        // when
+        let output = render_svg(&options);
+
+        // then
+        assert!(output.starts_with("<svg "));
+        assert!(output.ends_with("</svg>\n"));
+        assert_eq!(output.matches("<rect ").count(), 9);
+        assert_eq!(output.matches("fill=\"#ffd27f\"").count(), 3);
+        assert!(output.contains("<polyline points=\"100,100 60,100 60,60\""));
+    }
+
     #[test]
     fn move_up_should_move_up() {
         // given
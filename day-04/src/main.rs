@@ -0,0 +1,1382 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
+use std::fs::{read_to_string, File};
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+const CHUNK_LINES: usize = 4096;
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    if let Err(err) = run(&mut args) {
+        println!("{}", err);
+    }
+}
+
+fn run(args: &mut Vec<String>) -> Result<(), String> {
+    let threads = take_threads_option(args)?;
+    let seed = take_seed_option(args)?;
+    let filename = args.get(1).ok_or_else(|| "No file name given".to_owned())?;
+    let open = || {
+        File::open(Path::new(filename))
+            .map(BufReader::new)
+            .map_err(|e| e.to_string())
+    };
+
+    match args.get(2).map(|s| s.as_str()) {
+        None => {
+            let counts = count_valid_streaming(open()?, &[&NoDuplicates, &NoAnagrams], threads)
+                .map_err(|e| e.to_string())?;
+            println!("Number of valid phrases without duplicates: {}", counts[0]);
+            println!("Number of valid phrases without anagrams: {}", counts[1]);
+        }
+        Some("--policy") => {
+            let config_file = args
+                .get(3)
+                .ok_or_else(|| "No policy file given.".to_owned())?;
+            let config = read_to_string(Path::new(config_file)).map_err(|e| e.to_string())?;
+            let policy = parse_policy(&config)?;
+            let counts = count_valid_streaming(open()?, &[policy.as_ref()], threads)
+                .map_err(|e| e.to_string())?;
+            println!("Number of valid phrases: {}", counts[0]);
+        }
+        Some("--report") => {
+            let content = read_to_string(Path::new(filename)).map_err(|e| e.to_string())?;
+            let lines: Vec<&str> = content.lines().collect();
+            let report = conflict_report(&lines);
+            match args.get(3).map(|s| s.as_str()) {
+                None | Some("text") => print!("{}", format_report_text(&report)),
+                Some("json") => println!("{}", format_report_json(&report)),
+                Some(format) => return Err(format!("Unknown report format '{}'", format)),
+            }
+        }
+        Some("--bench") => {
+            let content = read_to_string(Path::new(filename)).map_err(|e| e.to_string())?;
+            bench(&content, threads)?;
+        }
+        Some("--classes") => {
+            let content = read_to_string(Path::new(filename)).map_err(|e| e.to_string())?;
+            let lines: Vec<&str> = content.lines().collect();
+            let classes = anagram_classes(&lines);
+            let listed: Vec<&AnagramClass> = match args.get(3).map(|s| s.as_str()) {
+                None => classes.iter().filter(|c| c.members.len() > 1).collect(),
+                Some("--cross-line") => classes.iter().filter(|c| c.spans_lines()).collect(),
+                Some(option) => return Err(format!("Unknown option '{}'", option)),
+            };
+            for class in &listed {
+                println!("{}", format_class(class));
+            }
+            println!(
+                "{} words in {} classes, {} listed",
+                classes.iter().map(|c| c.members.len()).sum::<usize>(),
+                classes.len(),
+                listed.len()
+            );
+        }
+        Some("--generate") => {
+            let count = parse_count(args.get(3), "passphrase count")?;
+            let word_count = parse_count(args.get(4), "word count")?;
+            let rule = match args.get(5).map(|s| s.as_str()) {
+                None | Some("no-duplicates") => GenerateRule::NoDuplicates,
+                Some("no-anagrams") => GenerateRule::NoAnagrams,
+                Some(rule) => return Err(format!("Unknown rule '{}'", rule)),
+            };
+            let content = read_to_string(Path::new(filename)).map_err(|e| e.to_string())?;
+            let words = word_list(&content);
+            let mut rng = Rng::new(seed);
+            let mut generator = PassphraseGenerator::new(&words, word_count, rule)?;
+            let bits = generator.entropy_bits();
+            for _ in 0..count {
+                let phrase = generator.generate(&mut rng);
+                println!("{} ({:.1} bits)", phrase.join(" "), bits);
+            }
+        }
+        Some(option) => return Err(format!("Unknown option '{}'", option)),
+    }
+
+    Ok(())
+}
+
+// Removes "<name> <value>" from the arguments wherever it appears and returns the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(format!("Expected a value after '{}'", name));
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+fn take_threads_option(args: &mut Vec<String>) -> Result<usize, String> {
+    match take_option(args, "--threads")? {
+        None => Ok(thread::available_parallelism().map_or(1, |n| n.get())),
+        Some(value) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Invalid thread count '{}'", value)),
+    }
+}
+
+fn take_seed_option(args: &mut Vec<String>) -> Result<u64, String> {
+    match take_option(args, "--seed")? {
+        None => Ok(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64)),
+        Some(value) => value
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid seed '{}': {}", value, e)),
+    }
+}
+
+fn parse_count(value: Option<&String>, what: &str) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("No {} given.", what))?;
+    value
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid {} '{}': {}", what, value, e))
+}
+
+// Compares the original BTreeMap based anagram check with the signature based one, both on a
+// single thread over the lines in memory and with the threaded streaming reader.
+fn bench(content: &str, threads: usize) -> Result<(), String> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    let start = Instant::now();
+    let reference = lines
+        .iter()
+        .filter(|phrase| passphrase_valid_by_char_maps(phrase))
+        .count();
+    let reference_time = start.elapsed();
+
+    let start = Instant::now();
+    let single = count_valid_passphrases(&lines, &NoAnagrams);
+    let single_time = start.elapsed();
+
+    let start = Instant::now();
+    let streamed = count_valid_streaming(content.as_bytes(), &[&NoAnagrams], threads)
+        .map_err(|e| e.to_string())?[0];
+    let streamed_time = start.elapsed();
+
+    println!("{} lines", lines.len());
+    let streamed_label = format!("signatures, {} threads:", threads);
+    println!(
+        "{:<28}{:?} ({} valid)",
+        "char maps:", reference_time, reference
+    );
+    println!("{:<28}{:?} ({} valid)", "signatures:", single_time, single);
+    println!(
+        "{:<28}{:?} ({} valid)",
+        streamed_label, streamed_time, streamed
+    );
+    if single != reference || streamed != reference {
+        return Err("Implementations disagree".to_owned());
+    }
+    Ok(())
+}
+
+// Reads the lines in chunks and hands them to `threads` workers in turn, each counting the lines
+// valid under every policy, so the input never has to be held in memory as a whole. Every worker
+// has a channel of its own, so no worker waits on a lock held by another one.
+fn count_valid_streaming<R: BufRead>(
+    reader: R,
+    policies: &[&dyn Policy],
+    threads: usize,
+) -> io::Result<Vec<usize>> {
+    thread::scope(|scope| {
+        let (senders, workers): (Vec<_>, Vec<_>) = (0..threads)
+            .map(|_| {
+                let (sender, receiver) = mpsc::sync_channel::<Vec<String>>(2);
+                let worker = scope.spawn(move || {
+                    let mut counts = vec![0; policies.len()];
+                    for chunk in receiver {
+                        for line in &chunk {
+                            let words: Vec<&str> = line.split_whitespace().collect();
+                            for (count, policy) in counts.iter_mut().zip(policies) {
+                                if policy.is_valid(&words) {
+                                    *count += 1;
+                                }
+                            }
+                        }
+                    }
+                    counts
+                });
+                (sender, worker)
+            })
+            .unzip();
+
+        // A send only fails if the worker is gone, which joining it reports below.
+        let mut result = Ok(());
+        let mut chunks_sent = 0;
+        let mut chunk = Vec::with_capacity(CHUNK_LINES);
+        for line in reader.lines() {
+            match line {
+                Ok(line) => chunk.push(line),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+            if chunk.len() == CHUNK_LINES {
+                let full = std::mem::replace(&mut chunk, Vec::with_capacity(CHUNK_LINES));
+                if senders[chunks_sent % threads].send(full).is_err() {
+                    break;
+                }
+                chunks_sent += 1;
+            }
+        }
+        if !chunk.is_empty() && result.is_ok() {
+            let _ = senders[chunks_sent % threads].send(chunk);
+        }
+        drop(senders);
+
+        let mut totals = vec![0; policies.len()];
+        let mut panicked = false;
+        for worker in workers {
+            match worker.join() {
+                Ok(counts) => {
+                    for (total, count) in totals.iter_mut().zip(counts) {
+                        *total += count;
+                    }
+                }
+                Err(_) => panicked = true,
+            }
+        }
+        if panicked {
+            return Err(io::Error::other("A worker thread panicked"));
+        }
+        result.map(|_| totals)
+    })
+}
+
+fn count_valid_passphrases(phrases: &[&str], policy: &dyn Policy) -> usize {
+    phrases
+        .iter()
+        .filter(|phrase| passphrase_valid(phrase, policy))
+        .count()
+}
+
+fn passphrase_valid(passphrase: &str, policy: &dyn Policy) -> bool {
+    let words: Vec<&str> = passphrase.split_whitespace().collect();
+    policy.is_valid(&words)
+}
+
+trait Policy: Sync {
+    fn is_valid(&self, words: &[&str]) -> bool;
+}
+
+struct NoDuplicates;
+
+impl Policy for NoDuplicates {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        let mut seen: HashSet<&str> = HashSet::with_capacity(words.len());
+        words.iter().all(|word| seen.insert(word))
+    }
+}
+
+struct NoDuplicatesIgnoringCase;
+
+impl Policy for NoDuplicatesIgnoringCase {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        let mut seen: HashSet<String> = HashSet::with_capacity(words.len());
+        words.iter().all(|word| seen.insert(word.to_lowercase()))
+    }
+}
+
+struct NoAnagrams;
+
+impl Policy for NoAnagrams {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        let mut seen: HashSet<Signature> = HashSet::with_capacity(words.len());
+        words
+            .iter()
+            .all(|word| seen.insert(anagram_signature(word)))
+    }
+}
+
+struct MinWordCount(usize);
+
+impl Policy for MinWordCount {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        words.len() >= self.0
+    }
+}
+
+struct MinWordLength(usize);
+
+impl Policy for MinWordLength {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        words.iter().all(|word| word.chars().count() >= self.0)
+    }
+}
+
+struct ForbiddenWords(HashSet<String>);
+
+impl Policy for ForbiddenWords {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        words.iter().all(|word| !self.0.contains(*word))
+    }
+}
+
+struct All(Vec<Box<dyn Policy>>);
+
+impl Policy for All {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        self.0.iter().all(|policy| policy.is_valid(words))
+    }
+}
+
+struct Any(Vec<Box<dyn Policy>>);
+
+impl Policy for Any {
+    fn is_valid(&self, words: &[&str]) -> bool {
+        self.0.iter().any(|policy| policy.is_valid(words))
+    }
+}
+
+// Two words are anagrams of each other exactly when their signatures are equal. Words made of
+// lowercase ASCII letters only are counted into a fixed array, which needs no allocation and
+// hashes cheaply; anything else falls back to the sorted characters.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Signature {
+    Letters([u8; 26]),
+    Chars(Vec<char>),
+}
+
+fn anagram_signature(word: &str) -> Signature {
+    if word.len() <= u8::MAX as usize && word.bytes().all(|b| b.is_ascii_lowercase()) {
+        let mut counts = [0u8; 26];
+        for b in word.bytes() {
+            counts[(b - b'a') as usize] += 1;
+        }
+        Signature::Letters(counts)
+    } else {
+        let mut chars: Vec<char> = word.chars().collect();
+        chars.sort_unstable();
+        Signature::Chars(chars)
+    }
+}
+
+// The original anagram check, kept as the reference for the benchmark.
+fn passphrase_valid_by_char_maps(passphrase: &str) -> bool {
+    let mut seen: HashSet<BTreeMap<char, u32>> = HashSet::new();
+    passphrase
+        .split_whitespace()
+        .all(|word| seen.insert(count_chars(word)))
+}
+
+fn count_chars(input: &str) -> BTreeMap<char, u32> {
+    let mut counter: BTreeMap<char, u32> = BTreeMap::new();
+    for c in input.chars() {
+        let entry = counter.entry(c).or_insert(0);
+        *entry += 1;
+    }
+    counter
+}
+
+// The distinct words of a file sharing one anagram signature, each with the 1-based numbers of
+// the lines it occurs on.
+#[derive(Debug, PartialEq, Eq)]
+struct AnagramClass {
+    members: Vec<ClassMember>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct ClassMember {
+    word: String,
+    lines: Vec<usize>,
+}
+
+impl AnagramClass {
+    // True if two different words of the class occur on different lines, which a single line
+    // check can never catch.
+    fn spans_lines(&self) -> bool {
+        self.members.iter().enumerate().any(|(i, a)| {
+            self.members[i + 1..]
+                .iter()
+                .any(|b| a.lines.iter().any(|la| b.lines.iter().any(|lb| la != lb)))
+        })
+    }
+}
+
+// Classes are sorted by their number of words, largest first, and then by their first word;
+// the words within a class are sorted alphabetically.
+fn anagram_classes(phrases: &[&str]) -> Vec<AnagramClass> {
+    let mut by_signature: HashMap<Signature, BTreeMap<&str, Vec<usize>>> = HashMap::new();
+    for (index, phrase) in phrases.iter().enumerate() {
+        for word in phrase.split_whitespace() {
+            let lines = by_signature
+                .entry(anagram_signature(word))
+                .or_default()
+                .entry(word)
+                .or_default();
+            if lines.last() != Some(&(index + 1)) {
+                lines.push(index + 1);
+            }
+        }
+    }
+
+    let mut classes: Vec<AnagramClass> = by_signature
+        .into_values()
+        .map(|words| AnagramClass {
+            members: words
+                .into_iter()
+                .map(|(word, lines)| ClassMember {
+                    word: word.to_owned(),
+                    lines,
+                })
+                .collect(),
+        })
+        .collect();
+    classes.sort_by(|a, b| {
+        b.members
+            .len()
+            .cmp(&a.members.len())
+            .then_with(|| a.members[0].word.cmp(&b.members[0].word))
+    });
+    classes
+}
+
+fn format_class(class: &AnagramClass) -> String {
+    let members: Vec<String> = class
+        .members
+        .iter()
+        .map(|member| {
+            let lines: Vec<String> = member.lines.iter().map(|l| l.to_string()).collect();
+            let label = if member.lines.len() == 1 {
+                "line"
+            } else {
+                "lines"
+            };
+            format!("{} ({} {})", member.word, label, lines.join(", "))
+        })
+        .collect();
+    format!("{}: {}", class.members.len(), members.join(", "))
+}
+
+// SplitMix64, which is tiny, passes the usual statistical tests and makes runs reproducible
+// from a single seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1), from the top 53 bits.
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Uniform in 0..bound, rejecting the top values that would bias the modulo.
+    fn below(&mut self, bound: usize) -> usize {
+        let bound = bound as u64;
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % bound) as usize;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GenerateRule {
+    NoDuplicates,
+    NoAnagrams,
+}
+
+// Draws phrases uniformly from all valid ones, so every phrase carries log2 of their number as
+// entropy. Without duplicates that is the number of k-permutations of the words, and a partial
+// Fisher-Yates shuffle of `words` picks one. Without anagrams it is k! times the sum, over every
+// choice of k anagram classes, of the product of the class sizes, i.e. the elementary symmetric
+// polynomial of the class sizes. Such a choice is drawn with a probability proportional to its
+// product, then one word of every chosen class, in random order.
+// The sums easily exceed an f64, so `log_sums[i][r]` holds the natural logarithm of the sum over
+// all choices of r classes from i on.
+struct PassphraseGenerator<'a> {
+    rule: GenerateRule,
+    word_count: usize,
+    words: Vec<&'a str>,
+    classes: Vec<Vec<&'a str>>,
+    log_sums: Vec<Vec<f64>>,
+}
+
+impl<'a> PassphraseGenerator<'a> {
+    fn new(
+        words: &'a [String],
+        word_count: usize,
+        rule: GenerateRule,
+    ) -> Result<PassphraseGenerator<'a>, String> {
+        if words.is_empty() {
+            return Err("The word list is empty".to_owned());
+        }
+        let mut generator = PassphraseGenerator {
+            rule,
+            word_count,
+            words: words.iter().map(|word| word.as_str()).collect(),
+            classes: Vec::new(),
+            log_sums: Vec::new(),
+        };
+        match rule {
+            GenerateRule::NoDuplicates => {
+                if word_count > words.len() {
+                    return Err(format!(
+                        "The word list has {} distinct words, too few for {} words without duplicates",
+                        words.len(),
+                        word_count
+                    ));
+                }
+            }
+            GenerateRule::NoAnagrams => {
+                generator.classes = word_classes(words);
+                if word_count > generator.classes.len() {
+                    return Err(format!(
+                        "The word list has {} anagram classes, too few for {} words without anagrams",
+                        generator.classes.len(),
+                        word_count
+                    ));
+                }
+                let classes = generator.classes.len();
+                let mut log_sums = vec![vec![f64::NEG_INFINITY; word_count + 1]; classes + 1];
+                log_sums[classes][0] = 0.0;
+                for i in (0..classes).rev() {
+                    let log_size = (generator.classes[i].len() as f64).ln();
+                    log_sums[i][0] = 0.0;
+                    for r in 1..=word_count {
+                        log_sums[i][r] =
+                            log_add(log_sums[i + 1][r], log_size + log_sums[i + 1][r - 1]);
+                    }
+                }
+                generator.log_sums = log_sums;
+            }
+        }
+        Ok(generator)
+    }
+
+    fn entropy_bits(&self) -> f64 {
+        let k = self.word_count;
+        match self.rule {
+            GenerateRule::NoDuplicates => {
+                let n = self.words.len();
+                (0..k).map(|i| ((n - i) as f64).log2()).sum()
+            }
+            GenerateRule::NoAnagrams => {
+                self.log_sums[0][k] / 2f64.ln() + (1..=k).map(|i| (i as f64).log2()).sum::<f64>()
+            }
+        }
+    }
+
+    fn generate(&mut self, rng: &mut Rng) -> Vec<&'a str> {
+        let k = self.word_count;
+        match self.rule {
+            // Starting from whatever order the previous phrase left behind keeps the draw uniform.
+            GenerateRule::NoDuplicates => {
+                for i in 0..k {
+                    let j = i + rng.below(self.words.len() - i);
+                    self.words.swap(i, j);
+                }
+                self.words[..k].to_vec()
+            }
+            GenerateRule::NoAnagrams => {
+                let mut phrase = Vec::with_capacity(k);
+                for (i, class) in self.classes.iter().enumerate() {
+                    let missing = k - phrase.len();
+                    if missing == 0 {
+                        break;
+                    }
+                    // Once the remaining classes are all needed, `log_sums[i][missing]` equals
+                    // `log_with` exactly, so the class is always taken.
+                    let log_with = (class.len() as f64).ln() + self.log_sums[i + 1][missing - 1];
+                    if rng.unit() < (log_with - self.log_sums[i][missing]).exp() {
+                        phrase.push(class[rng.below(class.len())]);
+                    }
+                }
+                for i in (1..phrase.len()).rev() {
+                    let j = rng.below(i + 1);
+                    phrase.swap(i, j);
+                }
+                phrase
+            }
+        }
+    }
+}
+
+// ln(e^a + e^b) without leaving the logarithms, where e^a = 0 is written as a = -inf.
+fn log_add(a: f64, b: f64) -> f64 {
+    if a == f64::NEG_INFINITY {
+        return b;
+    }
+    if b == f64::NEG_INFINITY {
+        return a;
+    }
+    a.max(b) + (-(a - b).abs()).exp().ln_1p()
+}
+
+// The distinct words of a word list, in the order they first appear.
+fn word_list(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    content
+        .split_whitespace()
+        .filter(|word| seen.insert(*word))
+        .map(|word| word.to_owned())
+        .collect()
+}
+
+// The words grouped by anagram signature, in the order the classes first appear, so that a seed
+// always draws the same phrases.
+fn word_classes(words: &[String]) -> Vec<Vec<&str>> {
+    let mut index_by_signature: HashMap<Signature, usize> = HashMap::new();
+    let mut classes: Vec<Vec<&str>> = Vec::new();
+    for word in words {
+        let index = *index_by_signature
+            .entry(anagram_signature(word))
+            .or_insert_with(|| {
+                classes.push(Vec::new());
+                classes.len() - 1
+            });
+        classes[index].push(word.as_str());
+    }
+    classes
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConflictKind {
+    Duplicate,
+    Anagram,
+}
+
+impl ConflictKind {
+    fn name(self) -> &'static str {
+        match self {
+            ConflictKind::Duplicate => "duplicate",
+            ConflictKind::Anagram => "anagram",
+        }
+    }
+}
+
+// Word indices are 1-based like line numbers, and `first` always precedes `second` on the line.
+#[derive(Debug, PartialEq, Eq)]
+struct Conflict {
+    kind: ConflictKind,
+    first_index: usize,
+    first_word: String,
+    second_index: usize,
+    second_word: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct InvalidLine {
+    line: usize,
+    conflicts: Vec<Conflict>,
+}
+
+// Pairs every word with the earliest preceding word it collides with, preferring an exact
+// duplicate over an anagram so "ab ba ab" reports "ab"/"ab" rather than "ab"/"ba" for word 3.
+fn find_conflicts(words: &[&str]) -> Vec<Conflict> {
+    let mut first_by_word: HashMap<&str, usize> = HashMap::new();
+    let mut first_by_signature: HashMap<Signature, usize> = HashMap::new();
+    let mut conflicts = Vec::new();
+    for (index, word) in words.iter().enumerate() {
+        let signature = anagram_signature(word);
+        let earlier = match first_by_word.get(word) {
+            Some(&first) => Some((ConflictKind::Duplicate, first)),
+            None => first_by_signature
+                .get(&signature)
+                .map(|&first| (ConflictKind::Anagram, first)),
+        };
+        match earlier {
+            Some((kind, first)) => conflicts.push(Conflict {
+                kind,
+                first_index: first + 1,
+                first_word: words[first].to_owned(),
+                second_index: index + 1,
+                second_word: (*word).to_owned(),
+            }),
+            None => {
+                first_by_signature.insert(signature, index);
+            }
+        }
+        first_by_word.entry(word).or_insert(index);
+    }
+    conflicts
+}
+
+fn conflict_report(phrases: &[&str]) -> Vec<InvalidLine> {
+    phrases
+        .iter()
+        .enumerate()
+        .filter_map(|(index, phrase)| {
+            let words: Vec<&str> = phrase.split_whitespace().collect();
+            let conflicts = find_conflicts(&words);
+            (!conflicts.is_empty()).then_some(InvalidLine {
+                line: index + 1,
+                conflicts,
+            })
+        })
+        .collect()
+}
+
+fn format_report_text(report: &[InvalidLine]) -> String {
+    let mut output = String::new();
+    for invalid in report {
+        for conflict in &invalid.conflicts {
+            output.push_str(&format!(
+                "line {}: {} of word {} '{}' and word {} '{}'\n",
+                invalid.line,
+                conflict.kind.name(),
+                conflict.first_index,
+                conflict.first_word,
+                conflict.second_index,
+                conflict.second_word
+            ));
+        }
+    }
+    output.push_str(&format!("{} invalid lines\n", report.len()));
+    output
+}
+
+fn format_report_json(report: &[InvalidLine]) -> String {
+    let lines: Vec<String> = report
+        .iter()
+        .map(|invalid| {
+            let conflicts: Vec<String> = invalid
+                .conflicts
+                .iter()
+                .map(|conflict| {
+                    format!(
+                        "{{\"kind\":\"{}\",\"first\":{{\"index\":{},\"word\":{}}},\"second\":{{\"index\":{},\"word\":{}}}}}",
+                        conflict.kind.name(),
+                        conflict.first_index,
+                        json_string(&conflict.first_word),
+                        conflict.second_index,
+                        json_string(&conflict.second_word)
+                    )
+                })
+                .collect();
+            format!(
+                "{{\"line\":{},\"conflicts\":[{}]}}",
+                invalid.line,
+                conflicts.join(",")
+            )
+        })
+        .collect();
+    format!("[{}]", lines.join(","))
+}
+
+fn json_string(input: &str) -> String {
+    let mut output = String::with_capacity(input.len() + 2);
+    output.push('"');
+    for c in input.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    output
+}
+
+// A policy config is an expression of rules combined with "and" and "or", where "and" binds
+// stronger, and parentheses. Everything after a '#' on a line is a comment. The rules are:
+//   no-duplicates, no-duplicates-ignoring-case, no-anagrams,
+//   min-words <n>, min-length <n>, forbidden <word>[,<word>...]
+// Example: "no-anagrams and (min-words 3 or forbidden foo,bar)"
+fn parse_policy(config: &str) -> Result<Box<dyn Policy>, String> {
+    let tokens = tokenize_policy(config);
+    let mut pos = 0;
+    let policy = parse_or(&tokens, &mut pos)?;
+    match tokens.get(pos) {
+        None => Ok(policy),
+        Some(token) => Err(format!("Unexpected '{}' in policy", token)),
+    }
+}
+
+fn tokenize_policy(config: &str) -> Vec<String> {
+    config
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| {
+            line.replace('(', " ( ")
+                .replace(')', " ) ")
+                .split_whitespace()
+                .map(|s| s.to_owned())
+                .collect::<Vec<String>>()
+        })
+        .collect()
+}
+
+fn parse_or(tokens: &[String], pos: &mut usize) -> Result<Box<dyn Policy>, String> {
+    let mut policies = vec![parse_and(tokens, pos)?];
+    while tokens.get(*pos).map(|s| s.as_str()) == Some("or") {
+        *pos += 1;
+        policies.push(parse_and(tokens, pos)?);
+    }
+    Ok(if policies.len() == 1 {
+        policies.remove(0)
+    } else {
+        Box::new(Any(policies))
+    })
+}
+
+fn parse_and(tokens: &[String], pos: &mut usize) -> Result<Box<dyn Policy>, String> {
+    let mut policies = vec![parse_rule(tokens, pos)?];
+    while tokens.get(*pos).map(|s| s.as_str()) == Some("and") {
+        *pos += 1;
+        policies.push(parse_rule(tokens, pos)?);
+    }
+    Ok(if policies.len() == 1 {
+        policies.remove(0)
+    } else {
+        Box::new(All(policies))
+    })
+}
+
+fn parse_rule(tokens: &[String], pos: &mut usize) -> Result<Box<dyn Policy>, String> {
+    let token = tokens
+        .get(*pos)
+        .ok_or_else(|| "Unexpected end of policy".to_owned())?;
+    *pos += 1;
+    match token.as_str() {
+        "(" => {
+            let policy = parse_or(tokens, pos)?;
+            if tokens.get(*pos).map(|s| s.as_str()) != Some(")") {
+                return Err("Missing ')' in policy".to_owned());
+            }
+            *pos += 1;
+            Ok(policy)
+        }
+        "no-duplicates" => Ok(Box::new(NoDuplicates)),
+        "no-duplicates-ignoring-case" => Ok(Box::new(NoDuplicatesIgnoringCase)),
+        "no-anagrams" => Ok(Box::new(NoAnagrams)),
+        "min-words" => Ok(Box::new(MinWordCount(parse_rule_number(
+            tokens, pos, token,
+        )?))),
+        "min-length" => Ok(Box::new(MinWordLength(parse_rule_number(
+            tokens, pos, token,
+        )?))),
+        "forbidden" => {
+            let words = tokens
+                .get(*pos)
+                .ok_or_else(|| "Expected a list of words after 'forbidden'".to_owned())?;
+            *pos += 1;
+            Ok(Box::new(ForbiddenWords(
+                words
+                    .split(',')
+                    .filter(|w| !w.is_empty())
+                    .map(|w| w.to_owned())
+                    .collect(),
+            )))
+        }
+        _ => Err(format!("Unknown policy rule '{}'", token)),
+    }
+}
+
+fn parse_rule_number(tokens: &[String], pos: &mut usize, rule: &str) -> Result<usize, String> {
+    let number = tokens
+        .get(*pos)
+        .ok_or_else(|| format!("Expected a number after '{}'", rule))?;
+    *pos += 1;
+    number
+        .parse::<usize>()
+        .map_err(|e| format!("Invalid number '{}' after '{}': {}", number, rule, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_valid_passphrases_counts_correctly_without_duplicates() {
+        // given
+        let phrases = &[" aa bb cc dd ee ", "aa bb cc dd aa", "aa bb cc dd aaa"];
+
+        // when
+        let count = count_valid_passphrases(phrases, &NoDuplicates);
+
+        // then
+        assert_eq!(count, 2);
+    }
+
+    #[test]
+    fn count_valid_passphrases_counts_correctly_without_anagrams() {
+        // given
+        let phrases = &[
+            " abcde fghjj ",
+            "abcde xyz ecdab",
+            "a ab abc abd abf abj",
+            "iiii oiii ooii oooi oooo",
+            "oiii ioii iioi iiio",
+        ];
+
+        // when
+        let count = count_valid_passphrases(phrases, &NoAnagrams);
+
+        // then
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn count_chars_should_count_a_words_chars() {
+        // given
+        let word = "foobar";
+
+        // when
+        let counter = count_chars(word);
+
+        // then
+        assert_eq!(counter.len(), 5);
+        assert_eq!(counter.get(&'f'), Some(&1));
+        assert_eq!(counter.get(&'o'), Some(&2));
+        assert_eq!(counter.get(&'b'), Some(&1));
+        assert_eq!(counter.get(&'a'), Some(&1));
+        assert_eq!(counter.get(&'r'), Some(&1));
+    }
+
+    #[test]
+    fn no_duplicates_should_recognize_valid_and_invalid_passphrases() {
+        // given
+        let valid_phrases = &[" aa bb cc dd ee ", "aa bb cc dd aaa"];
+        let invalid_phrase = "aa bb cc dd aa";
+
+        // when/then
+        for passphrase in valid_phrases {
+            assert!(passphrase_valid(passphrase, &NoDuplicates));
+        }
+        assert!(!passphrase_valid(invalid_phrase, &NoDuplicates));
+    }
+
+    #[test]
+    fn no_anagrams_should_recognize_valid_and_invalid_passphrases() {
+        // given
+        let valid_phrases = &[
+            " abcde fghij ",
+            "a ab abc abd abf abj",
+            "iiii oiii ooii oooi oooo",
+        ];
+        let invalid_phrases = &["abcde xyz ecdab", "oiii ioii iioi iiio"];
+
+        // when/then
+        for passphrase in valid_phrases {
+            assert!(passphrase_valid(passphrase, &NoAnagrams));
+        }
+        for passphrase in invalid_phrases {
+            assert!(!passphrase_valid(passphrase, &NoAnagrams));
+        }
+    }
+
+    #[test]
+    fn simple_rules_should_check_words() {
+        // given
+        let forbidden = ForbiddenWords(vec!["foo".to_owned()].into_iter().collect());
+
+        // when/then
+        assert!(!passphrase_valid("aa AA", &NoDuplicatesIgnoringCase));
+        assert!(passphrase_valid("aa AA", &NoDuplicates));
+        assert!(passphrase_valid("aa bb cc", &MinWordCount(3)));
+        assert!(!passphrase_valid("aa bb", &MinWordCount(3)));
+        assert!(passphrase_valid("aaa bbb", &MinWordLength(3)));
+        assert!(!passphrase_valid("aaa bb", &MinWordLength(3)));
+        assert!(passphrase_valid("foobar bar", &forbidden));
+        assert!(!passphrase_valid("bar foo", &forbidden));
+    }
+
+    #[test]
+    fn parse_policy_should_combine_rules() {
+        // given
+        let config = "# strict policy\nno-anagrams and (min-words 3 or forbidden foo,bar)\n";
+
+        // when
+        let policy = parse_policy(config).unwrap();
+
+        // then
+        assert!(passphrase_valid("ab cd ef", policy.as_ref()));
+        assert!(passphrase_valid("ab cd", policy.as_ref()));
+        assert!(!passphrase_valid("ab foo", policy.as_ref()));
+        assert!(!passphrase_valid("ab ba ef", policy.as_ref()));
+    }
+
+    #[test]
+    fn parse_policy_should_bind_and_stronger_than_or() {
+        // given
+        let config = "min-words 3 or min-length 2 and no-duplicates";
+
+        // when
+        let policy = parse_policy(config).unwrap();
+
+        // then
+        assert!(passphrase_valid("a a a", policy.as_ref()));
+        assert!(passphrase_valid("aa bb", policy.as_ref()));
+        assert!(!passphrase_valid("aa aa", policy.as_ref()));
+        assert!(!passphrase_valid("a bb", policy.as_ref()));
+    }
+
+    #[test]
+    fn parse_policy_should_reject_invalid_configs() {
+        // given
+        let configs = &[
+            "",
+            "no-anagrams and",
+            "(no-anagrams",
+            "no-anagrams)",
+            "min-words x",
+            "unknown-rule",
+        ];
+
+        // when/then
+        for config in configs {
+            assert!(parse_policy(config).is_err(), "config '{}'", config);
+        }
+    }
+
+    #[test]
+    fn find_conflicts_should_report_pairs_with_word_indices() {
+        // given
+        let words = &["abcde", "xyz", "ecdab", "xyz"];
+
+        // when
+        let conflicts = find_conflicts(words);
+
+        // then
+        assert_eq!(
+            conflicts,
+            vec![
+                Conflict {
+                    kind: ConflictKind::Anagram,
+                    first_index: 1,
+                    first_word: "abcde".to_owned(),
+                    second_index: 3,
+                    second_word: "ecdab".to_owned(),
+                },
+                Conflict {
+                    kind: ConflictKind::Duplicate,
+                    first_index: 2,
+                    first_word: "xyz".to_owned(),
+                    second_index: 4,
+                    second_word: "xyz".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn find_conflicts_should_prefer_duplicates_over_anagrams() {
+        // given
+        let words = &["ab", "ba", "ba"];
+
+        // when
+        let conflicts = find_conflicts(words);
+
+        // then
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].kind, ConflictKind::Anagram);
+        assert_eq!(
+            (conflicts[0].first_index, conflicts[0].second_index),
+            (1, 2)
+        );
+        assert_eq!(conflicts[1].kind, ConflictKind::Duplicate);
+        assert_eq!(
+            (conflicts[1].first_index, conflicts[1].second_index),
+            (2, 3)
+        );
+    }
+
+    #[test]
+    fn conflict_report_should_list_only_invalid_lines() {
+        // given
+        let phrases = &["aa bb", "aa bb aa", "", "ab \"ba"];
+
+        // when
+        let report = conflict_report(phrases);
+
+        // then
+        assert_eq!(
+            format_report_text(&report),
+            "line 2: duplicate of word 1 'aa' and word 3 'aa'\n\
+             1 invalid lines\n"
+        );
+        assert_eq!(
+            format_report_json(&report),
+            "[{\"line\":2,\"conflicts\":[{\"kind\":\"duplicate\",\
+             \"first\":{\"index\":1,\"word\":\"aa\"},\"second\":{\"index\":3,\"word\":\"aa\"}}]}]"
+        );
+    }
+
+    #[test]
+    fn json_string_should_escape_special_characters() {
+        // when/then
+        assert_eq!(json_string("a\"b\\c\u{1}"), "\"a\\\"b\\\\c\\u0001\"");
+    }
+
+    #[test]
+    fn anagram_signature_should_match_anagrams_only() {
+        // when/then
+        assert_eq!(anagram_signature("listen"), anagram_signature("silent"));
+        assert_ne!(anagram_signature("listen"), anagram_signature("listens"));
+        assert_eq!(anagram_signature("Über"), anagram_signature("rebÜ"));
+        assert_ne!(anagram_signature("Über"), anagram_signature("über"));
+        assert!(matches!(anagram_signature("abc"), Signature::Letters(_)));
+        assert!(matches!(anagram_signature("aBc"), Signature::Chars(_)));
+        let long = "a".repeat(300);
+        assert_eq!(anagram_signature(&long), Signature::Chars(vec!['a'; 300]));
+    }
+
+    #[test]
+    fn no_anagrams_should_agree_with_char_maps() {
+        // given
+        let phrases = &[
+            "abcde xyz ecdab",
+            "a ab abc abd abf abj",
+            "oiii ioii iioi iiio",
+            "Straße ßtraSe",
+            "Straße strasse",
+            "",
+        ];
+
+        // when/then
+        for passphrase in phrases {
+            assert_eq!(
+                passphrase_valid(passphrase, &NoAnagrams),
+                passphrase_valid_by_char_maps(passphrase),
+                "passphrase '{}'",
+                passphrase
+            );
+        }
+    }
+
+    #[test]
+    fn count_valid_streaming_should_count_every_policy_across_chunks() {
+        // given
+        let phrase_lines = [
+            "aa bb cc dd ee",
+            "aa bb cc dd aa",
+            "aa bb cc dd aaa",
+            "abcde xyz ecdab",
+        ];
+        let input: String = (0..CHUNK_LINES * 3 + 7)
+            .map(|i| format!("{}\n", phrase_lines[i % 4]))
+            .collect();
+        let expected_duplicates = (0..CHUNK_LINES * 3 + 7).filter(|i| i % 4 != 1).count();
+        let expected_anagrams = (0..CHUNK_LINES * 3 + 7).filter(|i| i % 2 == 0).count();
+
+        for threads in [1, 3] {
+            // when
+            let counts =
+                count_valid_streaming(input.as_bytes(), &[&NoDuplicates, &NoAnagrams], threads)
+                    .unwrap();
+
+            // then
+            assert_eq!(counts, vec![expected_duplicates, expected_anagrams]);
+        }
+    }
+
+    #[test]
+    fn count_valid_streaming_should_report_panicking_workers() {
+        // given
+        struct Panicking;
+        impl Policy for Panicking {
+            fn is_valid(&self, words: &[&str]) -> bool {
+                if words.contains(&"boom") {
+                    panic!("policy failed");
+                }
+                true
+            }
+        }
+        let input: String = (0..CHUNK_LINES * 4)
+            .map(|i| {
+                if i == CHUNK_LINES + 1 {
+                    "boom\n"
+                } else {
+                    "aa bb\n"
+                }
+            })
+            .collect();
+
+        // when
+        let result = count_valid_streaming(input.as_bytes(), &[&Panicking], 2);
+
+        // then
+        assert_eq!(
+            result.map_err(|e| e.to_string()),
+            Err("A worker thread panicked".to_owned())
+        );
+    }
+
+    #[test]
+    fn anagram_classes_should_group_words_of_all_lines() {
+        // given
+        let phrases = &["abc xy cab", "yx bca abc", "zz"];
+
+        // when
+        let classes = anagram_classes(phrases);
+
+        // then
+        let formatted: Vec<String> = classes.iter().map(format_class).collect();
+        assert_eq!(
+            formatted,
+            vec![
+                "3: abc (lines 1, 2), bca (line 2), cab (line 1)",
+                "2: xy (line 1), yx (line 2)",
+                "1: zz (line 3)",
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_lines_should_find_anagrams_on_different_lines() {
+        // given
+        let phrases = &["ab ba", "cd cd", "dc", "ef", "fe ef"];
+
+        // when
+        let classes = anagram_classes(phrases);
+
+        // then
+        let spanning: Vec<&str> = classes
+            .iter()
+            .filter(|c| c.spans_lines())
+            .map(|c| c.members[0].word.as_str())
+            .collect();
+        assert_eq!(spanning, vec!["cd", "ef"]);
+    }
+
+    #[test]
+    fn rng_should_be_reproducible_and_bounded() {
+        // given
+        let mut first = Rng::new(42);
+        let mut second = Rng::new(42);
+
+        // when
+        let a: Vec<usize> = (0..100).map(|_| first.below(7)).collect();
+        let b: Vec<usize> = (0..100).map(|_| second.below(7)).collect();
+
+        // then
+        assert_eq!(a, b);
+        assert!(a.iter().all(|&v| v < 7));
+        assert!((0..7).all(|v| a.contains(&v)));
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn passphrase_generator_should_honour_the_rule() {
+        // given
+        let words = word_list("abc bca cab xy yx zz abc");
+        let mut rng = Rng::new(7);
+        let mut generator = PassphraseGenerator::new(&words, 3, GenerateRule::NoAnagrams).unwrap();
+
+        for _ in 0..50 {
+            // when
+            let phrase = generator.generate(&mut rng);
+
+            // then
+            assert_eq!(phrase.len(), 3);
+            assert!(passphrase_valid(&phrase.join(" "), &NoAnagrams));
+        }
+        assert!(PassphraseGenerator::new(&words, 4, GenerateRule::NoAnagrams).is_err());
+        assert!(PassphraseGenerator::new(&words, 7, GenerateRule::NoDuplicates).is_err());
+    }
+
+    #[test]
+    fn passphrase_generator_should_use_every_word_when_asked_for_all() {
+        // given
+        let content: Vec<String> = (0..20).map(|i| format!("w{}", "x".repeat(i))).collect();
+        let words = word_list(&content.join(" "));
+        let mut rng = Rng::new(3);
+
+        for &rule in &[GenerateRule::NoDuplicates, GenerateRule::NoAnagrams] {
+            // when
+            let mut generator = PassphraseGenerator::new(&words, words.len(), rule).unwrap();
+            let mut phrase = generator.generate(&mut rng);
+
+            // then
+            phrase.sort();
+            let mut expected: Vec<&str> = words.iter().map(|w| w.as_str()).collect();
+            expected.sort();
+            assert_eq!(phrase, expected, "{:?}", rule);
+        }
+    }
+
+    #[test]
+    fn passphrase_generator_should_draw_valid_phrases_uniformly() {
+        // given
+        let words = word_list("abc bca cab xy yx zz");
+        let mut rng = Rng::new(11);
+        let mut generator = PassphraseGenerator::new(&words, 2, GenerateRule::NoAnagrams).unwrap();
+        let mut counts: HashMap<Vec<&str>, usize> = HashMap::new();
+
+        // when
+        for _ in 0..22_000 {
+            let phrase = generator.generate(&mut rng);
+            *counts.entry(phrase).or_default() += 1;
+        }
+
+        // then
+        // all 22 ordered pairs counted by entropy_bits, each about 1000 times
+        assert_eq!(counts.len(), 22);
+        assert!(
+            counts.values().all(|&n| n > 850 && n < 1150),
+            "{:?}",
+            counts
+        );
+    }
+
+    #[test]
+    fn entropy_bits_should_count_valid_phrases() {
+        // given
+        let words = word_list("abc bca cab xy yx zz");
+        let bits = |word_count, rule| {
+            PassphraseGenerator::new(&words, word_count, rule)
+                .unwrap()
+                .entropy_bits()
+        };
+
+        // when/then
+        // 6 * 5 = 30 ordered pairs of different words
+        assert!((bits(2, GenerateRule::NoDuplicates) - 30f64.log2()).abs() < 1e-9);
+        // class sizes 3, 2, 1: (3*2 + 3*1 + 2*1) * 2! = 22 ordered pairs
+        assert!((bits(2, GenerateRule::NoAnagrams) - 22f64.log2()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn passphrase_generator_should_handle_counts_beyond_f64() {
+        // given
+        // 2000 words of different lengths, so no two are anagrams
+        let content: Vec<String> = (1..=2000).map(|i| "a".repeat(i)).collect();
+        let words = word_list(&content.join(" "));
+        let mut rng = Rng::new(5);
+
+        // when
+        let mut generator =
+            PassphraseGenerator::new(&words, 300, GenerateRule::NoAnagrams).unwrap();
+        let bits = generator.entropy_bits();
+        let short_words: usize = (0..20)
+            .flat_map(|_| generator.generate(&mut rng))
+            .filter(|word| word.len() <= 1000)
+            .count();
+
+        // then
+        // with single word classes, both rules count the same 2000! / 1700! phrases
+        let expected = PassphraseGenerator::new(&words, 300, GenerateRule::NoDuplicates)
+            .unwrap()
+            .entropy_bits();
+        assert!(bits.is_finite());
+        assert!((bits - expected).abs() < 1e-6, "{} != {}", bits, expected);
+        // about half of the 6000 drawn words come from the first half of the list
+        assert!(short_words > 2800 && short_words < 3200, "{}", short_words);
+    }
+}
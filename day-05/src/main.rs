@@ -0,0 +1,1099 @@
+use std::convert::TryFrom;
+use std::env;
+use std::fs::{read_to_string, File};
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+const DEFAULT_CYCLE_MEMORY: usize = 256 * 1024 * 1024;
+
+fn main() {
+    let mut args: Vec<String> = env::args().collect();
+    if let Err(err) = run(&mut args) {
+        println!("{}", err);
+    }
+}
+
+fn run(args: &mut Vec<String>) -> Result<(), String> {
+    let limits = Limits {
+        step_budget: take_option(args, "--max-steps")?.map_or(Ok(u64::MAX), |v| {
+            v.parse::<u64>()
+                .map_err(|e| format!("Invalid step budget '{}': {}", v, e))
+        })?,
+        cycle_memory: take_option(args, "--max-memory")?.map_or(Ok(DEFAULT_CYCLE_MEMORY), |v| {
+            v.parse::<usize>()
+                .map_err(|e| format!("Invalid memory bound '{}': {}", v, e))
+        })?,
+    };
+    let trace_prefix = take_option(args, "--trace")?;
+    let trace_every = take_option(args, "--every")?.map_or(Ok(1), |v| {
+        v.parse::<u64>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("Invalid trace interval '{}'", v))
+    })?;
+    let compact = take_flag(args, "--compact");
+    if compact && trace_prefix.is_some() {
+        return Err("--compact cannot be combined with --trace".to_owned());
+    }
+    let filename = args.get(1).ok_or_else(|| "No file name given".to_owned())?;
+    let input =
+        read_to_string(Path::new(filename)).map_err(|e| format!("Unable to read input: {}", e))?;
+    let instructions = parse_lines(&input)?;
+    if args.get(2).map(|s| s.as_str()) == Some("--bench") {
+        bench(&instructions, limits.step_budget);
+        return Ok(());
+    }
+
+    let runs: Vec<(&str, Box<dyn OffsetRule>)> = match args.get(2).map(|s| s.as_str()) {
+        None => vec![
+            ("increment", Box::new(Increment)),
+            ("decrement-from-three", Box::new(DecrementFromThree)),
+        ],
+        Some("--rule") => {
+            let rule = args.get(3).ok_or_else(|| "No rule given".to_owned())?;
+            vec![("rule", parse_rule(rule)?)]
+        }
+        Some(option) => return Err(format!("Unknown option '{}'", option)),
+    };
+
+    for (label, rule) in &runs {
+        let outcome = match &trace_prefix {
+            None if compact => run_compact(&instructions, rule.as_ref(), limits.step_budget)?,
+            None => count_jumps_till_exit(instructions.clone(), rule.as_ref(), &limits)?,
+            Some(prefix) => run_traced(
+                &instructions,
+                rule.as_ref(),
+                &limits,
+                trace_every,
+                &format!("{}-{}", prefix, label),
+            )?,
+        };
+        if runs.len() > 1 {
+            println!("{}: {}", label, format_outcome(outcome));
+        } else {
+            println!("{}", format_outcome(outcome));
+        }
+    }
+
+    Ok(())
+}
+
+// Runs the program while writing "<base>-steps.csv" with every `every`-th step,
+// "<base>-visits.csv" with the number of jumps from each cell and "<base>-offsets.csv" with
+// the initial and final offset of each cell, and lists the hottest cells.
+fn run_traced(
+    instructions: &[i64],
+    rule: &dyn OffsetRule,
+    limits: &Limits,
+    every: u64,
+    base: &str,
+) -> Result<JumpOutcome, String> {
+    let write_error = |e: io::Error| format!("Unable to write trace: {}", e);
+    let steps = BufWriter::new(File::create(format!("{}-steps.csv", base)).map_err(write_error)?);
+    let mut trace = JumpTrace::new(every, steps, instructions.len()).map_err(write_error)?;
+    let outcome = count_jumps_traced(instructions.to_vec(), rule, limits, &mut trace)?;
+    let (visits, final_offsets) = trace.finish().map_err(write_error)?;
+    write_trace_csvs(instructions, &visits, &final_offsets, base).map_err(write_error)?;
+
+    println!("Hottest cells in {}-visits.csv:", base);
+    for (cell, count) in hottest_cells(&visits, 5) {
+        println!("  cell {}: {} jumps", cell, count);
+    }
+    Ok(outcome)
+}
+
+fn write_trace_csvs(
+    instructions: &[i64],
+    visits: &[u64],
+    final_offsets: &[i64],
+    base: &str,
+) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(format!("{}-visits.csv", base))?);
+    write_visits_csv(&mut out, visits)?;
+    out.flush()?;
+    let mut out = BufWriter::new(File::create(format!("{}-offsets.csv", base))?);
+    write_offsets_csv(&mut out, instructions, final_offsets)?;
+    out.flush()
+}
+
+// Runs a built-in rule on the compact engine, which only pays off for mazes with long settled
+// runs, see `count_jumps_compact`.
+fn run_compact(
+    instructions: &[i64],
+    rule: &dyn OffsetRule,
+    step_budget: u64,
+) -> Result<JumpOutcome, String> {
+    let compact = rule
+        .compact()
+        .ok_or_else(|| "--compact only runs the built-in rules".to_owned())?;
+    count_jumps_compact(instructions, compact, step_budget)
+        .ok_or_else(|| "An offset does not fit into an i32, run without --compact".to_owned())
+}
+
+// Removes "<name>" from the arguments wherever it appears and tells whether it was there.
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    match args.iter().position(|arg| arg == name) {
+        Some(index) => {
+            args.remove(index);
+            true
+        }
+        None => false,
+    }
+}
+
+// Removes "<name> <value>" from the arguments wherever it appears and returns the value.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    if index + 1 >= args.len() {
+        return Err(format!("Expected a value after '{}'", name));
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Ok(Some(value))
+}
+
+fn format_outcome(outcome: JumpOutcome) -> String {
+    match outcome {
+        JumpOutcome::Exited(steps) => format!("Number of steps to jump out: {}", steps),
+        JumpOutcome::Looping { first_seen, period } => format!(
+            "Never jumps out: the state after step {} repeats every {} steps",
+            first_seen, period
+        ),
+        JumpOutcome::BudgetExhausted => "Gave up before jumping out".to_owned(),
+    }
+}
+
+// Every line up to the last non-blank one has to hold an offset; anything else is reported
+// with its line number instead of being skipped, which would shift all later offsets.
+fn parse_lines(input: &str) -> Result<Vec<i64>, String> {
+    input
+        .trim_end()
+        .lines()
+        .enumerate()
+        .map(|(index, line)| {
+            line.trim()
+                .parse::<i64>()
+                .map_err(|e| format!("Line {}: invalid offset '{}': {}", index + 1, line, e))
+        })
+        .collect()
+}
+
+// Times the plain loop over a `Vec<i64>` against the compact engine for both built-in rules.
+fn bench(instructions: &[i64], step_budget: u64) {
+    let rules = [
+        ("increment", CompactRule::Increment),
+        ("decrement-from-three", CompactRule::DecrementFromThree),
+    ];
+    for (label, rule) in rules {
+        let plain_instructions = instructions.to_vec();
+        let start = Instant::now();
+        let plain = count_jumps_plain(plain_instructions, rule, step_budget);
+        let plain_time = start.elapsed();
+
+        let start = Instant::now();
+        let compact = count_jumps_compact(instructions, rule, step_budget);
+        let compact_time = start.elapsed();
+
+        println!("{}:", label);
+        match plain {
+            Some(plain) => println!("  Vec<i64>: {:?} ({})", plain_time, format_outcome(plain)),
+            None => println!("  Vec<i64>: an offset overflows i64"),
+        }
+        match compact {
+            Some(compact) => {
+                println!(
+                    "  compact:  {:?} ({})",
+                    compact_time,
+                    format_outcome(compact)
+                )
+            }
+            None => println!("  compact:  offsets do not fit into i32"),
+        }
+    }
+}
+
+// Decides what an offset becomes after the program jumped by it.
+// Returns `None` if the new offset does not fit into an `i64`.
+trait OffsetRule {
+    fn next_offset(&self, offset: i64) -> Option<i64>;
+
+    // The built-in rule this one behaves like, if any, which lets it run on the compact engine.
+    fn compact(&self) -> Option<CompactRule> {
+        None
+    }
+}
+
+struct Increment;
+
+impl OffsetRule for Increment {
+    fn next_offset(&self, offset: i64) -> Option<i64> {
+        offset.checked_add(1)
+    }
+
+    fn compact(&self) -> Option<CompactRule> {
+        Some(CompactRule::Increment)
+    }
+}
+
+struct DecrementFromThree;
+
+impl OffsetRule for DecrementFromThree {
+    fn next_offset(&self, offset: i64) -> Option<i64> {
+        if offset >= 3 {
+            Some(offset - 1)
+        } else {
+            offset.checked_add(1)
+        }
+    }
+
+    fn compact(&self) -> Option<CompactRule> {
+        Some(CompactRule::DecrementFromThree)
+    }
+}
+
+// Adds `base_delta` to an offset, unless the offset reaches one of the thresholds, in which case
+// the delta of the highest threshold reached is added instead.
+struct ThresholdTable {
+    base_delta: i64,
+    thresholds: Vec<(i64, i64)>,
+}
+
+impl OffsetRule for ThresholdTable {
+    fn next_offset(&self, offset: i64) -> Option<i64> {
+        let delta = self
+            .thresholds
+            .iter()
+            .rev()
+            .find(|(threshold, _)| offset >= *threshold)
+            .map_or(self.base_delta, |(_, delta)| *delta);
+        offset.checked_add(delta)
+    }
+
+    fn compact(&self) -> Option<CompactRule> {
+        match (self.base_delta, self.thresholds.as_slice()) {
+            (1, []) => Some(CompactRule::Increment),
+            (1, [(3, -1)]) => Some(CompactRule::DecrementFromThree),
+            _ => None,
+        }
+    }
+}
+
+// Accepts "increment", "decrement-from-three" or a table "DELTA[,THRESHOLD:DELTA...]", so
+// "1,3:-1" is the same as "decrement-from-three".
+fn parse_rule(input: &str) -> Result<Box<dyn OffsetRule>, String> {
+    match input {
+        "increment" => Ok(Box::new(Increment)),
+        "decrement-from-three" => Ok(Box::new(DecrementFromThree)),
+        table => {
+            let mut entries = table.split(',');
+            let base_delta = parse_number(entries.next().unwrap_or(""))?;
+            let mut thresholds = entries
+                .map(|entry| {
+                    let (threshold, delta) = entry
+                        .split_once(':')
+                        .ok_or_else(|| format!("Expected THRESHOLD:DELTA but got '{}'", entry))?;
+                    Ok((parse_number(threshold)?, parse_number(delta)?))
+                })
+                .collect::<Result<Vec<(i64, i64)>, String>>()?;
+            thresholds.sort_unstable();
+            if thresholds.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+                return Err(format!("Duplicate threshold in rule '{}'", table));
+            }
+            Ok(Box::new(ThresholdTable {
+                base_delta,
+                thresholds,
+            }))
+        }
+    }
+}
+
+fn parse_number(input: &str) -> Result<i64, String> {
+    input
+        .trim()
+        .parse::<i64>()
+        .map_err(|e| format!("Invalid number '{}': {}", input, e))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JumpOutcome {
+    Exited(u64),
+    // The state (position and instructions) after `first_seen` steps is the first one that
+    // comes back, which it does every `period` steps from then on.
+    Looping { first_seen: u64, period: u64 },
+    BudgetExhausted,
+}
+
+struct Limits {
+    step_budget: u64,
+    // Cycle detection keeps two extra copies of the instructions; if they would take more bytes
+    // than this, only the step budget stops a program that never exits.
+    cycle_memory: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            step_budget: u64::MAX,
+            cycle_memory: DEFAULT_CYCLE_MEMORY,
+        }
+    }
+}
+
+// The running program. Besides position and instructions it keeps a fingerprint of the
+// instructions, updated on every write, so two states can almost always be told apart
+// without comparing the whole instruction list.
+#[derive(Clone)]
+struct Machine {
+    pos: i64,
+    instructions: Vec<i64>,
+    fingerprint: u64,
+}
+
+impl Machine {
+    fn new(instructions: Vec<i64>) -> Self {
+        let fingerprint = instructions
+            .iter()
+            .enumerate()
+            .fold(0u64, |acc, (i, &v)| acc.wrapping_add(cell_hash(i, v)));
+        Machine {
+            pos: 0,
+            instructions,
+            fingerprint,
+        }
+    }
+
+    fn exited(&self) -> bool {
+        self.pos < 0 || self.pos >= self.instructions.len() as i64
+    }
+
+    // Leaves the machine unchanged if the new offset or position would overflow.
+    fn step(&mut self, rule: &dyn OffsetRule) -> Result<(), String> {
+        let index = self.pos as usize;
+        let offset = self.instructions[index];
+        let next = rule
+            .next_offset(offset)
+            .ok_or_else(|| format!("Offset {} in cell {} overflows when updated", offset, index))?;
+        let pos = self
+            .pos
+            .checked_add(offset)
+            .ok_or_else(|| format!("Jumping by {} from cell {} overflows", offset, index))?;
+        self.instructions[index] = next;
+        self.fingerprint = self
+            .fingerprint
+            .wrapping_sub(cell_hash(index, offset))
+            .wrapping_add(cell_hash(index, next));
+        self.pos = pos;
+        Ok(())
+    }
+
+    fn same_state(&self, other: &Machine) -> bool {
+        self.pos == other.pos
+            && self.fingerprint == other.fingerprint
+            && self.instructions == other.instructions
+    }
+}
+
+fn cell_hash(index: usize, value: i64) -> u64 {
+    let mut z = (index as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ value as u64;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CompactRule {
+    Increment,
+    DecrementFromThree,
+}
+
+// Under "decrement-from-three" a 2 becomes a 3 and a 3 becomes a 2, so once every cell of a
+// block is 2 or 3 it stays that way and the block is settled. Settled blocks are kept as one bit
+// per cell (set for 3), and a pass through one is a single lookup in `SETTLED_TRANSITIONS`.
+// Blocks of 8 cells keep the table at 4 KiB, small enough to stay in the cache.
+const SETTLED_BLOCK: usize = 8;
+
+// The state of a block that is not settled yet. Smaller states are the bits of a settled block.
+const UNSETTLED: u16 = 1 << SETTLED_BLOCK;
+
+// Indexed by the block's bits times `SETTLED_BLOCK` plus the cell the program enters at. Each
+// entry packs the block's new bits (bits 0-7), how far past the block's end the program lands
+// (bits 8-9) and the number of steps taken (bits 10-13).
+static SETTLED_TRANSITIONS: [u16; SETTLED_BLOCK << SETTLED_BLOCK] = settled_transitions();
+
+const fn settled_transitions() -> [u16; SETTLED_BLOCK << SETTLED_BLOCK] {
+    let mut table = [0u16; SETTLED_BLOCK << SETTLED_BLOCK];
+    let mut bits = 0;
+    while bits < 1 << SETTLED_BLOCK {
+        let mut entry = 0;
+        while entry < SETTLED_BLOCK {
+            let mut block = bits as u16;
+            let mut pos = entry;
+            let mut steps = 0;
+            while pos < SETTLED_BLOCK {
+                let offset = 2 + ((block >> pos) & 1) as usize;
+                block ^= 1 << pos;
+                pos += offset;
+                steps += 1;
+            }
+            let exit = (pos - SETTLED_BLOCK) as u16;
+            table[bits * SETTLED_BLOCK + entry] = block | (exit << 8) | (steps << 10);
+            entry += 1;
+        }
+        bits += 1;
+    }
+    table
+}
+
+// An engine for the built-in rules, which always exit, so it needs no cycle detection. Offsets
+// are stored as `i32`, which halves the memory of huge mazes, and runs of settled blocks are
+// passed in one go under "decrement-from-three". Every block counts its cells that are not 2 or
+// 3 yet, and settles as soon as that count drops to zero. A partial block at the end never
+// settles. This beats the plain loop when the program crosses long settled runs, as in the
+// puzzle's mazes. With mostly short jumps back, the settled runs are short, and switching
+// between single steps and passes makes it slower. Returns `None` if an offset does not fit
+// into an `i32`.
+fn count_jumps_compact(
+    instructions: &[i64],
+    rule: CompactRule,
+    step_budget: u64,
+) -> Option<JumpOutcome> {
+    let mut cells = instructions
+        .iter()
+        .map(|&offset| i32::try_from(offset).ok())
+        .collect::<Option<Vec<i32>>>()?;
+    let len = cells.len() as i64;
+    let mut steps: u64 = 0;
+    let mut pos: i64 = 0;
+
+    match rule {
+        CompactRule::Increment => {
+            while pos >= 0 && pos < len {
+                if steps >= step_budget {
+                    return Some(JumpOutcome::BudgetExhausted);
+                }
+                let cell = &mut cells[pos as usize];
+                let offset = *cell;
+                *cell = offset.checked_add(1)?;
+                pos += offset as i64;
+                steps += 1;
+            }
+        }
+        CompactRule::DecrementFromThree => {
+            let mut unsettled: Vec<u8> = cells
+                .chunks_exact(SETTLED_BLOCK)
+                .map(|block| {
+                    block
+                        .iter()
+                        .filter(|&&offset| offset != 2 && offset != 3)
+                        .count() as u8
+                })
+                .collect();
+            let mut blocks: Vec<u16> = cells
+                .chunks_exact(SETTLED_BLOCK)
+                .zip(&unsettled)
+                .map(|(block, &count)| {
+                    if count == 0 {
+                        settled_bits(block)
+                    } else {
+                        UNSETTLED
+                    }
+                })
+                .collect();
+            while pos >= 0 && pos < len {
+                let index = pos as usize;
+                let block = index / SETTLED_BLOCK;
+                if blocks.get(block).is_some_and(|&state| state < UNSETTLED) {
+                    // Settled blocks are only left forwards, so a run of them is passed in one go.
+                    let mut block = block;
+                    let mut entry = index % SETTLED_BLOCK;
+                    while let Some(state) =
+                        blocks.get_mut(block).filter(|state| **state < UNSETTLED)
+                    {
+                        let transition =
+                            SETTLED_TRANSITIONS[*state as usize * SETTLED_BLOCK + entry];
+                        *state = transition & 0xff;
+                        steps += (transition >> 10) as u64;
+                        entry = ((transition >> 8) & 3) as usize;
+                        block += 1;
+                        if steps > step_budget {
+                            return Some(JumpOutcome::BudgetExhausted);
+                        }
+                    }
+                    pos = (block * SETTLED_BLOCK + entry) as i64;
+                    continue;
+                }
+                if steps >= step_budget {
+                    return Some(JumpOutcome::BudgetExhausted);
+                }
+                let offset = cells[index];
+                cells[index] = if offset >= 3 {
+                    offset - 1
+                } else {
+                    offset.checked_add(1)?
+                };
+                pos += offset as i64;
+                steps += 1;
+                // Only a 1 or a 4 turns into a 2 or a 3.
+                if offset == 1 || offset == 4 {
+                    if let Some(count) = unsettled.get_mut(block) {
+                        *count -= 1;
+                        if *count == 0 {
+                            let start = block * SETTLED_BLOCK;
+                            blocks[block] = settled_bits(&cells[start..start + SETTLED_BLOCK]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Some(JumpOutcome::Exited(steps))
+}
+
+// The straightforward loop the compact engine is measured against: the offsets stay in the
+// `Vec<i64>` and are updated in place, step by step. Returns `None` if an offset or the position
+// overflows.
+fn count_jumps_plain(
+    mut instructions: Vec<i64>,
+    rule: CompactRule,
+    step_budget: u64,
+) -> Option<JumpOutcome> {
+    let mut steps: u64 = 0;
+    let mut pos: i64 = 0;
+
+    while pos >= 0 && pos < instructions.len() as i64 {
+        if steps >= step_budget {
+            return Some(JumpOutcome::BudgetExhausted);
+        }
+        let offset = instructions[pos as usize];
+        instructions[pos as usize] = match rule {
+            CompactRule::DecrementFromThree if offset >= 3 => offset - 1,
+            _ => offset.checked_add(1)?,
+        };
+        pos = pos.checked_add(offset)?;
+        steps += 1;
+    }
+
+    Some(JumpOutcome::Exited(steps))
+}
+
+fn settled_bits(block: &[i32]) -> u16 {
+    block
+        .iter()
+        .enumerate()
+        .fold(0, |bits, (i, &offset)| bits | (((offset == 3) as u16) << i))
+}
+
+// Sees every step of a run and the instructions it ended with, e.g. to trace it.
+trait StepObserver {
+    fn step(&mut self, step: u64, position: usize, offset: i64);
+    fn finish(&mut self, instructions: &[i64]);
+}
+
+struct NoTrace;
+
+impl StepObserver for NoTrace {
+    fn step(&mut self, _step: u64, _position: usize, _offset: i64) {}
+    fn finish(&mut self, _instructions: &[i64]) {}
+}
+
+// Writes every `every`-th step as "step,position,offset" CSV, where the offset is the one
+// jumped by, and counts the jumps from each cell. The first write error stops the writing and
+// is returned by `finish`.
+struct JumpTrace<W: Write> {
+    every: u64,
+    out: W,
+    visits: Vec<u64>,
+    final_offsets: Vec<i64>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> JumpTrace<W> {
+    fn new(every: u64, mut out: W, cells: usize) -> io::Result<Self> {
+        writeln!(out, "step,position,offset")?;
+        Ok(JumpTrace {
+            every,
+            out,
+            visits: vec![0; cells],
+            final_offsets: Vec::new(),
+            error: None,
+        })
+    }
+
+    fn finish(mut self) -> io::Result<(Vec<u64>, Vec<i64>)> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.out.flush()?;
+        Ok((self.visits, self.final_offsets))
+    }
+}
+
+impl<W: Write> StepObserver for JumpTrace<W> {
+    fn step(&mut self, step: u64, position: usize, offset: i64) {
+        self.visits[position] += 1;
+        if self.error.is_none() && step.is_multiple_of(self.every) {
+            if let Err(e) = writeln!(self.out, "{},{},{}", step, position, offset) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn finish(&mut self, instructions: &[i64]) {
+        self.final_offsets = instructions.to_vec();
+    }
+}
+
+fn write_visits_csv<W: Write>(out: &mut W, visits: &[u64]) -> io::Result<()> {
+    writeln!(out, "cell,visits")?;
+    for (cell, count) in visits.iter().enumerate() {
+        writeln!(out, "{},{}", cell, count)?;
+    }
+    Ok(())
+}
+
+fn write_offsets_csv<W: Write>(out: &mut W, initial: &[i64], last: &[i64]) -> io::Result<()> {
+    writeln!(out, "cell,initial,final")?;
+    for (cell, (first, last)) in initial.iter().zip(last).enumerate() {
+        writeln!(out, "{},{},{}", cell, first, last)?;
+    }
+    Ok(())
+}
+
+// The `count` most visited cells, most visited first and lower cells first on ties. Cells that
+// were never jumped from are left out.
+fn hottest_cells(visits: &[u64], count: usize) -> Vec<(usize, u64)> {
+    let mut cells: Vec<(usize, u64)> = visits
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|&(_, visits)| visits > 0)
+        .collect();
+    cells.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    cells.truncate(count);
+    cells
+}
+
+// Every rule runs on the general engine here. The compact engine is only used with --compact.
+fn count_jumps_till_exit(
+    instructions: Vec<i64>,
+    rule: &dyn OffsetRule,
+    limits: &Limits,
+) -> Result<JumpOutcome, String> {
+    count_jumps_traced(instructions, rule, limits, &mut NoTrace)
+}
+
+// Runs the program until it exits, provably loops or uses up the step budget. Loops are found
+// with Brent's algorithm: the state is compared against a snapshot taken at every power of two
+// steps, which finds the period. The first repeated state is then found by running two copies
+// of the program from the start, one of them `period` steps ahead, until they meet.
+// Only the run itself is observed, not the replay that locates the first repeated state.
+// A step whose new offset or position overflows an `i64` ends the run with an error.
+fn count_jumps_traced<O: StepObserver>(
+    instructions: Vec<i64>,
+    rule: &dyn OffsetRule,
+    limits: &Limits,
+    observer: &mut O,
+) -> Result<JumpOutcome, String> {
+    let detect_cycles =
+        instructions.len().saturating_mul(2 * size_of::<i64>()) <= limits.cycle_memory;
+    let initial = detect_cycles.then(|| instructions.clone());
+    let mut machine = Machine::new(instructions);
+    let mut snapshot = detect_cycles.then(|| machine.clone());
+    let mut steps: u64 = 0;
+    let mut power: u64 = 1;
+    let mut since_snapshot: u64 = 0;
+
+    let period = loop {
+        if machine.exited() {
+            observer.finish(&machine.instructions);
+            return Ok(JumpOutcome::Exited(steps));
+        }
+        if steps >= limits.step_budget {
+            observer.finish(&machine.instructions);
+            return Ok(JumpOutcome::BudgetExhausted);
+        }
+        let position = machine.pos as usize;
+        observer.step(steps, position, machine.instructions[position]);
+        machine
+            .step(rule)
+            .map_err(|e| format!("Step {}: {}", steps + 1, e))?;
+        steps += 1;
+        since_snapshot += 1;
+        if let Some(snapshot) = snapshot.as_mut() {
+            if machine.same_state(snapshot) {
+                break since_snapshot;
+            }
+            if since_snapshot == power {
+                snapshot.clone_from(&machine);
+                power *= 2;
+                since_snapshot = 0;
+            }
+        }
+    };
+    observer.finish(&machine.instructions);
+
+    let initial = initial.expect("cycles are only found while detecting them");
+    let mut behind = Machine::new(initial.clone());
+    let mut ahead = Machine::new(initial);
+    // The replay only repeats steps the run has already taken without overflowing.
+    for _ in 0..period {
+        ahead.step(rule)?;
+    }
+    let mut first_seen = 0;
+    while !behind.same_state(&ahead) {
+        behind.step(rule)?;
+        ahead.step(rule)?;
+        first_seen += 1;
+    }
+    Ok(JumpOutcome::Looping { first_seen, period })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn count_jumps_till_exit_counts_correctly_and_exits() {
+        // given
+        let instructions = vec![0, 3, 0, 2, -3];
+
+        // when
+        let jumps = count_jumps_till_exit(instructions, &Increment, &Limits::default());
+
+        // then
+        assert_eq!(jumps, Ok(JumpOutcome::Exited(5)));
+    }
+
+    #[test]
+    fn count_jumps_till_exit_counts_correctly_and_exits_with_decrements() {
+        // given
+        let instructions = vec![0, 3, 0, 2, -3];
+
+        // when
+        let jumps = count_jumps_till_exit(instructions, &DecrementFromThree, &Limits::default());
+
+        // then
+        assert_eq!(jumps, Ok(JumpOutcome::Exited(6)));
+    }
+
+    #[test]
+    fn parse_rule_should_build_equivalent_tables() {
+        // given
+        let instructions = vec![0, 3, 0, 2, -3, 7, -1, 4, -6];
+
+        // when
+        let increment = parse_rule("1").unwrap();
+        let decrement = parse_rule("1,3:-1").unwrap();
+
+        // then
+        assert_eq!(
+            count_jumps_till_exit(instructions.clone(), increment.as_ref(), &Limits::default()),
+            count_jumps_till_exit(instructions.clone(), &Increment, &Limits::default())
+        );
+        assert_eq!(
+            count_jumps_till_exit(instructions.clone(), decrement.as_ref(), &Limits::default()),
+            count_jumps_till_exit(instructions, &DecrementFromThree, &Limits::default())
+        );
+    }
+
+    #[test]
+    fn threshold_table_should_use_highest_threshold_reached() {
+        // given
+        let rule = parse_rule("2, 5:-1, 0:1").unwrap();
+
+        // when/then
+        assert_eq!(rule.next_offset(-4), Some(-2));
+        assert_eq!(rule.next_offset(0), Some(1));
+        assert_eq!(rule.next_offset(4), Some(5));
+        assert_eq!(rule.next_offset(5), Some(4));
+    }
+
+    #[test]
+    fn parse_rule_should_reject_invalid_tables() {
+        // when/then
+        for rule in ["", "x", "1,3", "1,3:x", "1,3:1,3:2"] {
+            assert!(parse_rule(rule).is_err(), "rule '{}'", rule);
+        }
+    }
+
+    // Finds the first repeated state by remembering every state, which is what the Brent based
+    // detection has to agree with.
+    fn naive_outcome(instructions: Vec<i64>, rule: &dyn OffsetRule) -> JumpOutcome {
+        let mut seen: HashMap<(i64, Vec<i64>), u64> = HashMap::new();
+        let mut machine = Machine::new(instructions);
+        let mut steps = 0;
+        loop {
+            if machine.exited() {
+                return JumpOutcome::Exited(steps);
+            }
+            let state = (machine.pos, machine.instructions.clone());
+            if let Some(&first_seen) = seen.get(&state) {
+                return JumpOutcome::Looping {
+                    first_seen,
+                    period: steps - first_seen,
+                };
+            }
+            seen.insert(state, steps);
+            machine.step(rule).unwrap();
+            steps += 1;
+        }
+    }
+
+    #[test]
+    fn count_jumps_till_exit_should_detect_loops() {
+        // given
+        let programs: &[(&[i64], &str)] = &[
+            (&[0], "0"),
+            (&[1, -1], "0"),
+            (&[2, 5, 1, -1], "0"),
+            (&[2, 0, 1, 1, -1], "-1,0:0"),
+            (&[2, 2, 1, -3], "0,2:-1"),
+            (&[1, 2, 2, -3, -2], "0,2:-1"),
+            (&[2, 0, -1, 1, -3], "1,1:-1,3:-2"),
+            (&[0, 3, 0, 2, -3], "1,3:-1"),
+        ];
+
+        for (instructions, rule) in programs {
+            let rule = parse_rule(rule).unwrap();
+
+            // when
+            let outcome =
+                count_jumps_till_exit(instructions.to_vec(), rule.as_ref(), &Limits::default());
+
+            // then
+            assert_eq!(
+                outcome,
+                Ok(naive_outcome(instructions.to_vec(), rule.as_ref())),
+                "program {:?}",
+                instructions
+            );
+        }
+        assert_eq!(
+            count_jumps_till_exit(
+                vec![3, 2, 1, -3],
+                parse_rule("0,2:-1").unwrap().as_ref(),
+                &Limits::default()
+            ),
+            Ok(JumpOutcome::Looping {
+                first_seen: 7,
+                period: 4
+            })
+        );
+    }
+
+    #[test]
+    fn count_jumps_till_exit_should_respect_the_limits() {
+        // given
+        let rule = parse_rule("0").unwrap();
+        let no_memory = Limits {
+            step_budget: 1000,
+            cycle_memory: 0,
+        };
+        let budget = Limits {
+            step_budget: 4,
+            cycle_memory: DEFAULT_CYCLE_MEMORY,
+        };
+
+        // when/then
+        assert_eq!(
+            count_jumps_till_exit(vec![1, -1], rule.as_ref(), &no_memory),
+            Ok(JumpOutcome::BudgetExhausted)
+        );
+        assert_eq!(
+            count_jumps_till_exit(vec![0, 3, 0, 2, -3], &Increment, &budget),
+            Ok(JumpOutcome::BudgetExhausted)
+        );
+        assert_eq!(
+            count_jumps_till_exit(vec![0, 3, 0, 2, -3], &Increment, &no_memory),
+            Ok(JumpOutcome::Exited(5))
+        );
+    }
+
+    #[test]
+    fn count_jumps_till_exit_should_report_overflow() {
+        // given
+        let keep = parse_rule("0").unwrap();
+        let add_five = parse_rule("5").unwrap();
+
+        // when/then
+        assert_eq!(
+            count_jumps_till_exit(vec![i64::MAX], &Increment, &Limits::default()),
+            Err(format!(
+                "Step 1: Offset {} in cell 0 overflows when updated",
+                i64::MAX
+            ))
+        );
+        assert_eq!(
+            count_jumps_till_exit(vec![i64::MAX - 4, 1], add_five.as_ref(), &Limits::default()),
+            Err(format!(
+                "Step 1: Offset {} in cell 0 overflows when updated",
+                i64::MAX - 4
+            ))
+        );
+        assert_eq!(
+            count_jumps_till_exit(vec![1, i64::MAX], keep.as_ref(), &Limits::default()),
+            Err(format!(
+                "Step 2: Jumping by {} from cell 1 overflows",
+                i64::MAX
+            ))
+        );
+    }
+
+    #[test]
+    fn jump_trace_should_record_steps_visits_and_final_offsets() {
+        // given
+        let mut trace = JumpTrace::new(2, Vec::new(), 5).unwrap();
+
+        // when
+        let outcome = count_jumps_traced(
+            vec![0, 3, 0, 2, -3],
+            &Increment,
+            &Limits::default(),
+            &mut trace,
+        );
+
+        // then
+        assert_eq!(outcome, Ok(JumpOutcome::Exited(5)));
+        assert_eq!(
+            String::from_utf8(trace.out.clone()).unwrap(),
+            "step,position,offset\n0,0,0\n2,1,3\n4,1,4\n"
+        );
+        let (visits, final_offsets) = trace.finish().unwrap();
+        assert_eq!(visits, vec![2, 2, 0, 0, 1]);
+        assert_eq!(final_offsets, vec![2, 5, 0, 2, -2]);
+        assert_eq!(hottest_cells(&visits, 2), vec![(0, 2), (1, 2)]);
+        assert_eq!(hottest_cells(&visits, 5), vec![(0, 2), (1, 2), (4, 1)]);
+    }
+
+    #[test]
+    fn trace_csv_writers_should_list_every_cell() {
+        // given
+        let mut visits = Vec::new();
+        let mut offsets = Vec::new();
+
+        // when
+        write_visits_csv(&mut visits, &[2, 0]).unwrap();
+        write_offsets_csv(&mut offsets, &[0, 3], &[2, 3]).unwrap();
+
+        // then
+        assert_eq!(
+            String::from_utf8(visits).unwrap(),
+            "cell,visits\n0,2\n1,0\n"
+        );
+        assert_eq!(
+            String::from_utf8(offsets).unwrap(),
+            "cell,initial,final\n0,0,2\n1,3,3\n"
+        );
+    }
+
+    #[test]
+    fn parse_lines_should_report_invalid_lines() {
+        // when/then
+        assert_eq!(parse_lines("0\n3\n -3 \n\n"), Ok(vec![0, 3, -3]));
+        assert_eq!(
+            parse_lines("0\n3\nx\n2"),
+            Err("Line 3: invalid offset 'x': invalid digit found in string".to_owned())
+        );
+        assert!(parse_lines("0\n\n2").unwrap_err().starts_with("Line 2:"));
+    }
+
+    #[test]
+    fn count_jumps_compact_should_agree_with_the_general_engine() {
+        // given
+        let mut seed: u64 = 5;
+        let mut random = |bound: i64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((seed >> 33) % bound as u64) as i64
+        };
+        let rules: [(&dyn OffsetRule, CompactRule); 2] = [
+            (&Increment, CompactRule::Increment),
+            (&DecrementFromThree, CompactRule::DecrementFromThree),
+        ];
+
+        for length in [1, 5, 16, 17, 40, 200, 1000] {
+            let instructions: Vec<i64> = (0..length).map(|_| random(9) - 6).collect();
+            for (rule, compact) in rules {
+                // when
+                let outcome = count_jumps_compact(&instructions, compact, u64::MAX);
+
+                // then
+                let expected = count_jumps_traced(
+                    instructions.clone(),
+                    rule,
+                    &Limits::default(),
+                    &mut NoTrace,
+                );
+                assert_eq!(
+                    outcome,
+                    expected.ok(),
+                    "{:?} on {:?}",
+                    compact,
+                    instructions
+                );
+                assert_eq!(
+                    count_jumps_plain(instructions.clone(), compact, u64::MAX),
+                    outcome,
+                    "plain {:?} on {:?}",
+                    compact,
+                    instructions
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn count_jumps_compact_should_respect_the_budget_and_i32_range() {
+        // given
+        let settled = vec![2; 64];
+
+        // when/then
+        assert_eq!(
+            count_jumps_compact(&settled, CompactRule::DecrementFromThree, 100),
+            Some(JumpOutcome::Exited(32))
+        );
+        assert_eq!(
+            count_jumps_compact(&settled, CompactRule::DecrementFromThree, 32),
+            Some(JumpOutcome::Exited(32))
+        );
+        assert_eq!(
+            count_jumps_compact(&settled, CompactRule::DecrementFromThree, 31),
+            Some(JumpOutcome::BudgetExhausted)
+        );
+        assert_eq!(
+            count_jumps_compact(&[1 << 40], CompactRule::Increment, 100),
+            None
+        );
+        assert_eq!(
+            count_jumps_till_exit(vec![1 << 40], &Increment, &Limits::default()),
+            Ok(JumpOutcome::Exited(1))
+        );
+        assert_eq!(
+            parse_rule("1,3:-1").unwrap().compact(),
+            Some(CompactRule::DecrementFromThree)
+        );
+        assert_eq!(parse_rule("1,2:-1").unwrap().compact(), None);
+        assert_eq!(
+            run_compact(&[1 << 40], &Increment, 100),
+            Err("An offset does not fit into an i32, run without --compact".to_owned())
+        );
+        assert_eq!(
+            run_compact(&[2; 64], parse_rule("1,2:-1").unwrap().as_ref(), 100),
+            Err("--compact only runs the built-in rules".to_owned())
+        );
+        assert_eq!(
+            count_jumps_plain(vec![i64::MAX], CompactRule::Increment, 100),
+            None
+        );
+        assert_eq!(
+            count_jumps_plain(vec![0, 3, 0, 2, -3], CompactRule::Increment, 4),
+            Some(JumpOutcome::BudgetExhausted)
+        );
+    }
+}
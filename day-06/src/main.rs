@@ -1,34 +1,237 @@
 use std::collections::HashMap;
 use std::env;
-use std::fs::read_to_string;
+use std::fs::{read_to_string, File};
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
 fn main() -> Result<(), String> {
-    let filename = env::args()
-        .nth(1)
+    let args: Vec<String> = env::args().collect();
+    let filename = args
+        .get(1)
         .ok_or_else(|| "No file name given.".to_owned())?;
-    let content = read_to_string(&Path::new(&filename)).map_err(|e| e.to_string())?;
+    let mut method = CycleMethod::Brent;
+    let mut policy = Policy::default();
+    let mut history_file: Option<&String> = None;
+    let mut state_at: Option<u64> = None;
+    for option in args[2..].chunks(2) {
+        let value = option
+            .get(1)
+            .ok_or_else(|| format!("No value given for '{}'", option[0]))?;
+        match option[0].as_str() {
+            "--method" => method = CycleMethod::parse(value)?,
+            "--select" | "--ties" | "--direction" => policy.set(&option[0], value)?,
+            "--history" => history_file = Some(value),
+            "--state-at" => {
+                state_at = Some(
+                    value
+                        .parse::<u64>()
+                        .map_err(|e| format!("Invalid step '{}': {}", value, e))?,
+                )
+            }
+            other => return Err(format!("Unknown option '{}'", other)),
+        }
+    }
+    let content = read_to_string(Path::new(filename)).map_err(|e| e.to_string())?;
     let banks: Vec<u32> = content
         .split_whitespace()
         .filter(|s| !s.is_empty())
         .map(|s| s.parse::<u32>().map_err(|e| e.to_string()))
         .collect::<Result<Vec<u32>, String>>()?;
 
-    let (relocates_until_repeat, loop_size) = count_relocate_until_repeat(banks);
+    if history_file.is_none() && state_at.is_none() {
+        let (relocates_until_repeat, loop_size) =
+            method.count_relocate_until_repeat(banks, &policy);
+        println!(
+            "{} relocates until the memory pattern repeats, loop_size is {}",
+            relocates_until_repeat, loop_size,
+        );
+        return Ok(());
+    }
+
+    let history = History::record(banks, &policy, method);
     println!(
         "{} relocates until the memory pattern repeats, loop_size is {}",
-        relocates_until_repeat, loop_size,
+        history.prefix_len() + history.loop_size(),
+        history.loop_size(),
     );
+    if let Some(step) = state_at {
+        println!(
+            "State after {} relocates: {:?}",
+            step,
+            history.state_at(step)
+        );
+    }
+    if let Some(history_file) = history_file {
+        let mut out = BufWriter::new(File::create(history_file).map_err(|e| e.to_string())?);
+        history
+            .write_csv(&mut out)
+            .and_then(|_| out.flush())
+            .map_err(|e| e.to_string())?;
+    }
 
     Ok(())
 }
 
-fn count_relocate_until_repeat(banks: Vec<u32>) -> (usize, usize) {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CycleMethod {
+    HashMap,
+    Floyd,
+    Brent,
+}
+
+impl CycleMethod {
+    fn parse(name: &str) -> Result<CycleMethod, String> {
+        match name {
+            "hash-map" => Ok(CycleMethod::HashMap),
+            "floyd" => Ok(CycleMethod::Floyd),
+            "brent" => Ok(CycleMethod::Brent),
+            _ => Err(format!(
+                "Unknown method '{}', expected hash-map, floyd or brent",
+                name
+            )),
+        }
+    }
+
+    fn count_relocate_until_repeat(self, banks: Vec<u32>, policy: &Policy) -> (usize, usize) {
+        match self {
+            CycleMethod::HashMap => count_relocate_until_repeat(banks, policy),
+            CycleMethod::Floyd => count_relocate_until_repeat_floyd(banks, policy),
+            CycleMethod::Brent => count_relocate_until_repeat_brent(banks, policy),
+        }
+    }
+}
+
+// Which bank gets emptied. A random selection is derived from the seed and the banks alone,
+// so that relocating stays a function of the banks and the cycle detection still applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Selection {
+    Max,
+    MinNonZero,
+    Random(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TieBreak {
+    LowestIndex,
+    HighestIndex,
+}
+
+// In which order the banks receive the blocks. Both sides alternates between the next bank to
+// the right and the next bank to the left of the emptied one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    Forwards,
+    Backwards,
+    BothSides,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Policy {
+    selection: Selection,
+    tie_break: TieBreak,
+    direction: Direction,
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            selection: Selection::Max,
+            tie_break: TieBreak::LowestIndex,
+            direction: Direction::Forwards,
+        }
+    }
+}
+
+impl Policy {
+    // Applies one "--select", "--ties" or "--direction" option.
+    fn set(&mut self, option: &str, value: &str) -> Result<(), String> {
+        match (option, value) {
+            ("--select", "max") => self.selection = Selection::Max,
+            ("--select", "min-non-zero") => self.selection = Selection::MinNonZero,
+            ("--select", random) if random.starts_with("random:") => {
+                let seed = &random["random:".len()..];
+                self.selection = Selection::Random(
+                    seed.parse::<u64>()
+                        .map_err(|e| format!("Invalid seed '{}': {}", seed, e))?,
+                )
+            }
+            ("--ties", "lowest") => self.tie_break = TieBreak::LowestIndex,
+            ("--ties", "highest") => self.tie_break = TieBreak::HighestIndex,
+            ("--direction", "forwards") => self.direction = Direction::Forwards,
+            ("--direction", "backwards") => self.direction = Direction::Backwards,
+            ("--direction", "both") => self.direction = Direction::BothSides,
+            _ => return Err(format!("Invalid value '{}' for '{}'", value, option)),
+        }
+        Ok(())
+    }
+
+    fn select(&self, banks: &[u32]) -> Option<usize> {
+        let preferred = |(il, bl): &(usize, &u32), (ir, br): &(usize, &u32)| match self.tie_break {
+            TieBreak::LowestIndex => il < ir,
+            TieBreak::HighestIndex => il > ir,
+        } && bl == br;
+        let candidates = banks.iter().enumerate();
+        let chosen = match self.selection {
+            Selection::Max => candidates.reduce(|best, bank| {
+                if bank.1 > best.1 || preferred(&bank, &best) {
+                    bank
+                } else {
+                    best
+                }
+            }),
+            Selection::MinNonZero => candidates.filter(|(_, &b)| b > 0).reduce(|best, bank| {
+                if bank.1 < best.1 || preferred(&bank, &best) {
+                    bank
+                } else {
+                    best
+                }
+            }),
+            Selection::Random(seed) => {
+                let non_zero = banks.iter().filter(|&&b| b > 0).count();
+                if non_zero == 0 {
+                    return None;
+                }
+                let pick = (mix_banks(seed, banks) % non_zero as u64) as usize;
+                candidates.filter(|(_, &b)| b > 0).nth(pick)
+            }
+        };
+        chosen.map(|(index, _)| index)
+    }
+
+    // The bank receiving the block handed out `step` places after the emptied bank at `index`,
+    // for `step` below the number of banks; the emptied bank itself comes last.
+    fn receiver(&self, index: usize, n_banks: usize, step: usize) -> usize {
+        match self.direction {
+            Direction::Forwards => (index + 1 + step) % n_banks,
+            Direction::Backwards => (index + n_banks - 1 - step) % n_banks,
+            Direction::BothSides if step + 1 == n_banks => index,
+            Direction::BothSides => {
+                let distance = step / 2 + 1;
+                if step.is_multiple_of(2) {
+                    (index + distance) % n_banks
+                } else {
+                    (index + n_banks - distance) % n_banks
+                }
+            }
+        }
+    }
+}
+
+fn mix_banks(seed: u64, banks: &[u32]) -> u64 {
+    banks.iter().fold(seed, |hash, &bank| {
+        let mut z = (hash ^ bank as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    })
+}
+
+fn count_relocate_until_repeat(banks: Vec<u32>, policy: &Policy) -> (usize, usize) {
     let mut current: Vec<u32> = banks;
     let mut seen: HashMap<Vec<u32>, usize> = HashMap::with_capacity(256);
     while !seen.contains_key(&current) {
         seen.insert(current.clone(), seen.len());
-        current = relocate(current);
+        current = relocate(current, policy);
     }
     (
         seen.len(),
@@ -39,25 +242,161 @@ fn count_relocate_until_repeat(banks: Vec<u32>) -> (usize, usize) {
     )
 }
 
-fn relocate(mut banks: Vec<u32>) -> Vec<u32> {
-    let (index, max_blocks): (usize, u32) = match banks.iter().enumerate().max_by(
-        |(il, bl), (ir, br)| {
-            if bl == br {
-                ir.cmp(il)
-            } else {
-                bl.cmp(br)
+// Floyd's tortoise and hare: the hare relocates twice as often as the tortoise until they
+// meet, which happens at a multiple of the loop size. Then a tortoise from the start and one
+// from the meeting point meet at the first repeated state, and walking on gives the loop size.
+// Only two states are kept at any time.
+fn count_relocate_until_repeat_floyd(banks: Vec<u32>, policy: &Policy) -> (usize, usize) {
+    let mut tortoise = relocate(banks.clone(), policy);
+    let mut hare = relocate(relocate(banks.clone(), policy), policy);
+    while tortoise != hare {
+        tortoise = relocate(tortoise, policy);
+        hare = relocate(relocate(hare, policy), policy);
+    }
+
+    let mut first_seen = 0;
+    tortoise = banks;
+    while tortoise != hare {
+        tortoise = relocate(tortoise, policy);
+        hare = relocate(hare, policy);
+        first_seen += 1;
+    }
+
+    let mut loop_size = 1;
+    hare = relocate(hare, policy);
+    while tortoise != hare {
+        hare = relocate(hare, policy);
+        loop_size += 1;
+    }
+    (first_seen + loop_size, loop_size)
+}
+
+// Brent's algorithm: the hare is compared against a tortoise that teleports to it whenever the
+// number of relocations since the last teleport reaches the next power of two, which yields
+// the loop size directly. The first repeated state is then found by starting a second copy
+// `loop_size` relocations ahead of the first. Like Floyd's it keeps only two states, but it
+// needs fewer relocations.
+fn count_relocate_until_repeat_brent(banks: Vec<u32>, policy: &Policy) -> (usize, usize) {
+    let mut power = 1;
+    let mut loop_size = 1;
+    let mut tortoise = banks.clone();
+    let mut hare = relocate(banks.clone(), policy);
+    while tortoise != hare {
+        if power == loop_size {
+            tortoise = hare.clone();
+            power *= 2;
+            loop_size = 0;
+        }
+        hare = relocate(hare, policy);
+        loop_size += 1;
+    }
+
+    let mut first_seen = 0;
+    tortoise = banks.clone();
+    hare = banks;
+    for _ in 0..loop_size {
+        hare = relocate(hare, policy);
+    }
+    while tortoise != hare {
+        tortoise = relocate(tortoise, policy);
+        hare = relocate(hare, policy);
+        first_seen += 1;
+    }
+    (first_seen + loop_size, loop_size)
+}
+
+// All states the banks go through: the prefix leading up to the loop and the states of the loop,
+// which then repeat forever.
+#[derive(Debug, PartialEq, Eq)]
+struct History {
+    prefix: Vec<Vec<u32>>,
+    cycle: Vec<Vec<u32>>,
+}
+
+impl History {
+    // Detects the loop with `method` first, so the states can be recorded without looking any
+    // of them up.
+    fn record(banks: Vec<u32>, policy: &Policy, method: CycleMethod) -> History {
+        let (relocates_until_repeat, loop_size) =
+            method.count_relocate_until_repeat(banks.clone(), policy);
+        let mut states = Vec::with_capacity(relocates_until_repeat);
+        let mut current = banks;
+        for _ in 1..relocates_until_repeat {
+            let next = relocate(current.clone(), policy);
+            states.push(current);
+            current = next;
+        }
+        states.push(current);
+        let cycle = states.split_off(relocates_until_repeat - loop_size);
+        History {
+            prefix: states,
+            cycle,
+        }
+    }
+
+    fn prefix_len(&self) -> usize {
+        self.prefix.len()
+    }
+
+    fn loop_size(&self) -> usize {
+        self.cycle_states().len()
+    }
+
+    fn cycle_states(&self) -> &[Vec<u32>] {
+        &self.cycle
+    }
+
+    // The banks after `step` relocations.
+    fn state_at(&self, step: u64) -> &[u32] {
+        match usize::try_from(step) {
+            Ok(step) if step < self.prefix.len() => &self.prefix[step],
+            _ => {
+                let into_cycle = (step - self.prefix.len() as u64) % self.cycle.len() as u64;
+                &self.cycle[into_cycle as usize]
+            }
+        }
+    }
+
+    // One row per distinct state: the step, whether it is part of the loop and the banks.
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let n_banks = self.cycle.first().map_or(0, |state| state.len());
+        write!(out, "step,in_loop")?;
+        for bank in 0..n_banks {
+            write!(out, ",bank_{}", bank)?;
+        }
+        writeln!(out)?;
+        let states = self.prefix.iter().map(|state| (false, state));
+        let states = states.chain(self.cycle.iter().map(|state| (true, state)));
+        for (step, (in_loop, state)) in states.enumerate() {
+            write!(out, "{},{}", step, in_loop)?;
+            for blocks in state {
+                write!(out, ",{}", blocks)?;
             }
-        },
-    ) {
-        Some((i, m)) => (i, *m),
+            writeln!(out)?;
+        }
+        Ok(())
+    }
+}
+
+fn relocate(mut banks: Vec<u32>, policy: &Policy) -> Vec<u32> {
+    let index = match policy.select(&banks) {
+        Some(index) => index,
         None => {
             return banks;
         }
     };
+    // Handing out the blocks one by one, every bank gets `quotient` of them, and the first
+    // `remainder` receivers after the emptied one get one more.
     let n_banks = banks.len();
+    let blocks = banks[index];
+    let quotient = blocks / n_banks as u32;
+    let remainder = blocks as usize % n_banks;
     banks[index] = 0;
-    for i in 1..=(max_blocks as usize) {
-        banks[(index + i) % n_banks] += 1;
+    for bank in banks.iter_mut() {
+        *bank += quotient;
+    }
+    for step in 0..remainder {
+        banks[policy.receiver(index, n_banks, step)] += 1;
     }
     banks
 }
@@ -72,7 +411,7 @@ mod test {
         let banks = vec![0, 2, 7, 0];
 
         // when
-        let result = relocate(banks);
+        let result = relocate(banks, &Policy::default());
 
         // then
         assert_eq!(&result, &[2, 4, 1, 2]);
@@ -84,10 +423,316 @@ mod test {
         let banks = vec![0, 2, 7, 0];
 
         // when
-        let (count, loop_size) = count_relocate_until_repeat(banks);
+        let (count, loop_size) = count_relocate_until_repeat(banks, &Policy::default());
 
         // then
         assert_eq!(count, 5);
         assert_eq!(loop_size, 4);
     }
+
+    #[test]
+    fn cycle_methods_agree_with_hash_map() {
+        // given
+        let examples: &[&[u32]] = &[
+            &[0, 2, 7, 0],
+            &[],
+            &[5],
+            &[0, 0, 0],
+            &[3, 1, 4, 1, 5, 9, 2, 6],
+            &[10, 3, 15, 10, 5, 15, 5, 15, 9, 2, 5, 8, 5, 2, 3, 6],
+        ];
+
+        for banks in examples {
+            for policy in all_policies() {
+                // a random selection wanders through far too many states on many banks
+                if banks.len() > 8 && matches!(policy.selection, Selection::Random(_)) {
+                    continue;
+                }
+                let expected = count_relocate_until_repeat(banks.to_vec(), &policy);
+                for method in [CycleMethod::Floyd, CycleMethod::Brent] {
+                    // when
+                    let result = method.count_relocate_until_repeat(banks.to_vec(), &policy);
+
+                    // then
+                    assert_eq!(
+                        result, expected,
+                        "{:?} with {:?} on {:?}",
+                        method, policy, banks
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cycle_method_parses_names() {
+        // when/then
+        assert_eq!(CycleMethod::parse("hash-map"), Ok(CycleMethod::HashMap));
+        assert_eq!(CycleMethod::parse("floyd"), Ok(CycleMethod::Floyd));
+        assert_eq!(CycleMethod::parse("brent"), Ok(CycleMethod::Brent));
+        assert!(CycleMethod::parse("rho").is_err());
+    }
+
+    // The original relocation, handing out the blocks one at a time.
+    fn relocate_one_by_one(mut banks: Vec<u32>) -> Vec<u32> {
+        let (index, max_blocks) =
+            match banks.iter().enumerate().max_by(
+                |(il, bl), (ir, br)| {
+                    if bl == br {
+                        ir.cmp(il)
+                    } else {
+                        bl.cmp(br)
+                    }
+                },
+            ) {
+                Some((i, m)) => (i, *m),
+                None => {
+                    return banks;
+                }
+            };
+        let n_banks = banks.len();
+        banks[index] = 0;
+        for i in 1..=(max_blocks as usize) {
+            banks[(index + i) % n_banks] += 1;
+        }
+        banks
+    }
+
+    // xorshift64*, good enough to generate test inputs.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: u32) -> u32 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 32) as u32 % bound
+        }
+    }
+
+    #[test]
+    fn relocate_matches_one_by_one_relocation_on_random_banks() {
+        // given
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..2000 {
+            let len = random.below(20) as usize;
+            let max_value = [3, 20, 1000, 100_000][random.below(4) as usize];
+            let banks: Vec<u32> = (0..len).map(|_| random.below(max_value)).collect();
+
+            // when
+            let result = relocate(banks.clone(), &Policy::default());
+
+            // then
+            assert_eq!(
+                result,
+                relocate_one_by_one(banks.clone()),
+                "banks {:?}",
+                banks
+            );
+        }
+    }
+
+    #[test]
+    fn relocate_handles_huge_banks() {
+        // given
+        let banks = vec![1, 4_000_000_003, 2];
+
+        // when
+        let result = relocate(banks, &Policy::default());
+
+        // then
+        assert_eq!(result, vec![1_333_333_335, 1_333_333_334, 1_333_333_337]);
+    }
+
+    fn all_policies() -> Vec<Policy> {
+        let mut policies = Vec::new();
+        for selection in [Selection::Max, Selection::MinNonZero, Selection::Random(7)] {
+            for tie_break in [TieBreak::LowestIndex, TieBreak::HighestIndex] {
+                for direction in [
+                    Direction::Forwards,
+                    Direction::Backwards,
+                    Direction::BothSides,
+                ] {
+                    policies.push(Policy {
+                        selection,
+                        tie_break,
+                        direction,
+                    });
+                }
+            }
+        }
+        policies
+    }
+
+    #[test]
+    fn policy_selects_banks() {
+        // given
+        let banks = [0, 3, 1, 3, 1];
+        let policy = |selection, tie_break| Policy {
+            selection,
+            tie_break,
+            direction: Direction::Forwards,
+        };
+
+        // when/then
+        assert_eq!(
+            policy(Selection::Max, TieBreak::LowestIndex).select(&banks),
+            Some(1)
+        );
+        assert_eq!(
+            policy(Selection::Max, TieBreak::HighestIndex).select(&banks),
+            Some(3)
+        );
+        assert_eq!(
+            policy(Selection::MinNonZero, TieBreak::LowestIndex).select(&banks),
+            Some(2)
+        );
+        assert_eq!(
+            policy(Selection::MinNonZero, TieBreak::HighestIndex).select(&banks),
+            Some(4)
+        );
+        assert_eq!(
+            policy(Selection::MinNonZero, TieBreak::LowestIndex).select(&[0, 0]),
+            None
+        );
+        let random = policy(Selection::Random(3), TieBreak::LowestIndex);
+        let picked = random.select(&banks).unwrap();
+        assert_ne!(banks[picked], 0);
+        assert_eq!(random.select(&banks), Some(picked));
+        assert_eq!(random.select(&[0, 0, 5]), Some(2));
+    }
+
+    #[test]
+    fn policy_distributes_in_direction() {
+        // given
+        let banks = vec![0, 0, 7, 0, 0];
+        let policy = |direction| Policy {
+            direction,
+            ..Policy::default()
+        };
+
+        // when/then
+        assert_eq!(
+            relocate(banks.clone(), &policy(Direction::Forwards)),
+            vec![1, 1, 1, 2, 2]
+        );
+        assert_eq!(
+            relocate(banks.clone(), &policy(Direction::Backwards)),
+            vec![2, 2, 1, 1, 1]
+        );
+        assert_eq!(
+            relocate(banks, &policy(Direction::BothSides)),
+            vec![1, 2, 1, 2, 1]
+        );
+        for n_banks in 1..8 {
+            for index in 0..n_banks {
+                let mut receivers: Vec<usize> = (0..n_banks)
+                    .map(|step| policy(Direction::BothSides).receiver(index, n_banks, step))
+                    .collect();
+                assert_eq!(receivers[n_banks - 1], index);
+                receivers.sort_unstable();
+                assert_eq!(receivers, (0..n_banks).collect::<Vec<usize>>());
+            }
+        }
+    }
+
+    #[test]
+    fn policy_parses_options() {
+        // given
+        let mut policy = Policy::default();
+
+        // when
+        policy.set("--select", "random:42").unwrap();
+        policy.set("--ties", "highest").unwrap();
+        policy.set("--direction", "both").unwrap();
+
+        // then
+        assert_eq!(
+            policy,
+            Policy {
+                selection: Selection::Random(42),
+                tie_break: TieBreak::HighestIndex,
+                direction: Direction::BothSides,
+            }
+        );
+        assert!(policy.set("--select", "random:x").is_err());
+        assert!(policy.set("--direction", "up").is_err());
+    }
+
+    #[test]
+    fn history_records_prefix_and_cycle() {
+        // given
+        let banks = vec![0, 2, 7, 0];
+
+        // when
+        let history = History::record(banks, &Policy::default(), CycleMethod::Brent);
+
+        // then
+        assert_eq!(history.prefix_len(), 1);
+        assert_eq!(history.loop_size(), 4);
+        assert_eq!(
+            history.cycle_states(),
+            &[
+                vec![2, 4, 1, 2],
+                vec![3, 1, 2, 3],
+                vec![0, 2, 3, 4],
+                vec![1, 3, 4, 1],
+            ]
+        );
+        for method in [CycleMethod::HashMap, CycleMethod::Floyd] {
+            assert_eq!(
+                History::record(vec![0, 2, 7, 0], &Policy::default(), method),
+                history
+            );
+        }
+    }
+
+    #[test]
+    fn history_answers_any_step() {
+        // given
+        let banks = vec![3, 1, 4, 1, 5, 9, 2, 6];
+        let policy = Policy::default();
+        let history = History::record(banks.clone(), &policy, CycleMethod::Brent);
+
+        // when/then
+        let mut state = banks;
+        for step in 0..3 * (history.prefix_len() + history.loop_size()) as u64 {
+            assert_eq!(history.state_at(step), &state[..], "step {}", step);
+            state = relocate(state, &policy);
+        }
+        let huge = 1_000_000_000_000_000_000u64;
+        let into_cycle = (huge - history.prefix_len() as u64) % history.loop_size() as u64;
+        assert_eq!(
+            history.state_at(huge),
+            &history.cycle_states()[into_cycle as usize][..]
+        );
+        assert_eq!(
+            history.state_at(u64::MAX),
+            &history.cycle_states()
+                [((u64::MAX - history.prefix_len() as u64) % history.loop_size() as u64) as usize]
+                [..]
+        );
+    }
+
+    #[test]
+    fn history_writes_csv() {
+        // given
+        let history = History::record(vec![0, 2, 7, 0], &Policy::default(), CycleMethod::Brent);
+        let mut out = Vec::new();
+
+        // when
+        history.write_csv(&mut out).unwrap();
+
+        // then
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "step,in_loop,bank_0,bank_1,bank_2,bank_3\n\
+             0,false,0,2,7,0\n\
+             1,true,2,4,1,2\n\
+             2,true,3,1,2,3\n\
+             3,true,0,2,3,4\n\
+             4,true,1,3,4,1\n"
+        );
+    }
 }